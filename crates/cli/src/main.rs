@@ -0,0 +1,498 @@
+//! `strip-whitespace` command-line interface.
+//!
+//! Strips whitespace from Astro/Svelte/Vue/HTML files. By default the stripped code of a single
+//! file is printed to stdout; `--write` rewrites files in place, `--check` reports files that would
+//! change (for CI), and `--diff` prints unified diffs.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use similar::TextDiff;
+use strip_whitespace::{
+    AttributeWhitespace, Diagnostic, GapStrategy, Language as LanguageInner, StripConfig,
+    StripError, TagAction, TagRule, TextWhitespace, strip_whitespace,
+    strip_whitespace_no_sourcemap,
+};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Language {
+    Astro,
+    Svelte,
+    Vue,
+    Html,
+}
+
+impl Language {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "astro" => Some(Language::Astro),
+            "svelte" => Some(Language::Svelte),
+            "vue" => Some(Language::Vue),
+            "html" | "htm" => Some(Language::Html),
+            _ => None,
+        }
+    }
+}
+
+impl From<Language> for LanguageInner {
+    fn from(value: Language) -> Self {
+        match value {
+            Language::Astro => LanguageInner::Astro,
+            Language::Svelte => LanguageInner::Svelte,
+            Language::Vue => LanguageInner::Vue,
+            Language::Html => LanguageInner::Html,
+        }
+    }
+}
+
+/// How `--collapse-text` rewrites whitespace runs inside text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollapseText {
+    /// Collapse each run to a single space
+    Space,
+    /// Collapse each run to a single line break if it contains one, else to a space
+    Newline,
+}
+
+impl From<CollapseText> for TextWhitespace {
+    fn from(value: CollapseText) -> Self {
+        match value {
+            CollapseText::Space => TextWhitespace::CollapseToSpace,
+            CollapseText::Newline => TextWhitespace::CollapseToNewline,
+        }
+    }
+}
+
+/// How `--collapse-attributes` rewrites whitespace between attributes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum CollapseAttributes {
+    /// Collapse each run to a single space
+    Space,
+    /// Collapse runs within a line, keeping line breaks and indentation
+    KeepLines,
+}
+
+impl From<CollapseAttributes> for AttributeWhitespace {
+    fn from(value: CollapseAttributes) -> Self {
+        match value {
+            CollapseAttributes::Space => AttributeWhitespace::CollapseToSpace,
+            CollapseAttributes::KeepLines => AttributeWhitespace::KeepLineBreaks,
+        }
+    }
+}
+
+/// How `--gaps` rewrites whitespace between nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Gaps {
+    /// Move the whitespace inside the neighbouring tags, keeping line/column positions
+    #[default]
+    Rotate,
+    /// Delete the whitespace (smaller output; rely on the sourcemap for positions)
+    Delete,
+}
+
+impl From<Gaps> for GapStrategy {
+    fn from(value: Gaps) -> Self {
+        match value {
+            Gaps::Rotate => GapStrategy::Rotate,
+            Gaps::Delete => GapStrategy::Delete,
+        }
+    }
+}
+
+/// The action of a `--tag-rule`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum TagRuleAction {
+    /// Strip the element, even if it is whitespace-sensitive
+    Strip,
+    /// Leave the element and the gaps around it untouched
+    Preserve,
+    /// Strip the element's contents but not the gaps around it
+    StripChildren,
+    /// Strip the gaps around the element but not its contents
+    StripOutside,
+    /// Collapse the gaps inside the element to a single space
+    CollapseToSpace,
+}
+
+impl From<TagRuleAction> for TagAction {
+    fn from(value: TagRuleAction) -> Self {
+        match value {
+            TagRuleAction::Strip => TagAction::Strip,
+            TagRuleAction::Preserve => TagAction::Preserve,
+            TagRuleAction::StripChildren => TagAction::StripChildren,
+            TagRuleAction::StripOutside => TagAction::StripOutside,
+            TagRuleAction::CollapseToSpace => TagAction::CollapseToSpace,
+        }
+    }
+}
+
+/// Parses a `--tag-rule` value of the form `PATTERN=ACTION`.
+fn parse_tag_rule(value: &str) -> Result<TagRule, String> {
+    let (pattern, action) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected PATTERN=ACTION, got `{value}`"))?;
+    let action = <TagRuleAction as clap::ValueEnum>::from_str(action, true)?;
+    Ok(TagRule::new(pattern, action.into()))
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "strip-whitespace", version)]
+#[command(about = "Strip whitespace between markup nodes while preserving line/col as much as possible", long_about = None)]
+struct Args {
+    /// Files, directories or glob patterns to process
+    #[arg(required = true)]
+    paths: Vec<String>,
+
+    /// Override language instead of inferring from file extensions
+    #[arg(long, short)]
+    language: Option<Language>,
+
+    /// Preserve blank-line gaps ("\n\n" / "\r\n\r\n") between nodes
+    #[arg(long)]
+    preserve_blank_lines: bool,
+
+    /// How whitespace gaps between nodes are rewritten
+    #[arg(long, value_name = "STRATEGY", default_value = "rotate")]
+    gaps: Gaps,
+
+    /// Also collapse whitespace runs between words inside text
+    #[arg(long, value_name = "MODE")]
+    collapse_text: Option<CollapseText>,
+
+    /// Also collapse whitespace between the attributes of start tags
+    #[arg(long, value_name = "MODE")]
+    collapse_attributes: Option<CollapseAttributes>,
+
+    /// Delete whitespace between text and block-level element boundaries instead of moving it
+    #[arg(long)]
+    trim_block_text: bool,
+
+    /// Keep a single space between inline elements instead of removing the gap
+    #[arg(long)]
+    inline_safe: bool,
+
+    /// Component name to treat as block-level (repeatable)
+    #[arg(long = "block-component", value_name = "NAME")]
+    block_components: Vec<String>,
+
+    /// Remove `data-strip-whitespace` marker attributes from the output
+    #[arg(long)]
+    remove_markers: bool,
+
+    /// Remove HTML comments, except directives, conditional and `<!--! ... -->` comments
+    #[arg(long)]
+    remove_comments: bool,
+
+    /// Per-tag rule such as `Trans=collapse-to-space` (repeatable; first match wins). Actions:
+    /// strip, preserve, strip-children, strip-outside, collapse-to-space
+    #[arg(long = "tag-rule", value_name = "PATTERN=ACTION", value_parser = parse_tag_rule)]
+    tag_rules: Vec<TagRule>,
+
+    /// Rewrite files in place
+    #[arg(long, short, conflicts_with = "check")]
+    write: bool,
+
+    /// Exit with a non-zero status if any file would change, without writing anything
+    #[arg(long)]
+    check: bool,
+
+    /// Print a unified diff for every file that would change
+    #[arg(long)]
+    diff: bool,
+
+    /// Also write a sourcemap next to each rewritten file (`<file>.map`)
+    #[arg(long, requires = "write")]
+    sourcemap: bool,
+}
+
+impl Args {
+    /// Returns true if no mode flag is given, so the stripped code is printed to stdout.
+    fn prints_code(&self) -> bool {
+        !self.write && !self.check && !self.diff
+    }
+}
+
+/// Exit status when `--check` finds files that would change.
+const EXIT_WOULD_CHANGE: u8 = 1;
+
+/// Exit status when a path could not be read, parsed or written.
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let files = match collect_files(&args.paths) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    // Concatenated outputs could not be told apart.
+    if args.prints_code() && files.len() > 1 {
+        eprintln!(
+            "error: {} files matched; pass --write, --check or --diff to process more than one",
+            files.len()
+        );
+        return ExitCode::from(EXIT_ERROR);
+    }
+
+    let cfg = StripConfig {
+        preserve_blank_lines: args.preserve_blank_lines,
+        text_whitespace: args
+            .collapse_text
+            .map_or(TextWhitespace::Preserve, Into::into),
+        trim_block_text: args.trim_block_text,
+        inline_safe: args.inline_safe,
+        block_level_components: args.block_components.clone(),
+        remove_marker_attributes: args.remove_markers,
+        remove_comments: args.remove_comments,
+        tag_rules: args.tag_rules.clone(),
+        gap_strategy: args.gaps.into(),
+        attribute_whitespace: args
+            .collapse_attributes
+            .map_or(AttributeWhitespace::Preserve, Into::into),
+        ..Default::default()
+    };
+
+    let mut changed = 0usize;
+    let mut failed = false;
+    for path in &files {
+        match process_file(path, &args, &cfg) {
+            Ok(true) => changed += 1,
+            Ok(false) => {}
+            Err(e) => {
+                match e.downcast_ref::<Diagnostic>() {
+                    Some(diag) => eprintln!("{}\n", diag.render()),
+                    None => eprintln!("error: {}: {e}", path.display()),
+                }
+                failed = true;
+            }
+        }
+    }
+
+    if args.check && changed > 0 {
+        eprintln!("{changed} of {} file(s) would be changed", files.len());
+    }
+
+    if failed {
+        ExitCode::from(EXIT_ERROR)
+    } else if args.check && changed > 0 {
+        ExitCode::from(EXIT_WOULD_CHANGE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Strips a single file according to `args`. Returns whether the output differs from the input.
+fn process_file(path: &Path, args: &Args, cfg: &StripConfig) -> Result<bool, Error> {
+    let language: LanguageInner = match args.language {
+        Some(lang) => lang,
+        None => path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Language::from_extension)
+            .ok_or("could not infer language from file extension; pass --language")?,
+    }
+    .into();
+
+    let source = fs::read_to_string(path)?;
+    let diagnostic = |e: StripError| Diagnostic::new(&e, &source, &path.display().to_string());
+
+    let (code, map) = if args.sourcemap {
+        let file_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or("file name is not valid UTF-8")?;
+        let res = strip_whitespace(&source, file_name, language, cfg).map_err(diagnostic)?;
+        (res.code, Some(res.map))
+    } else {
+        let code = strip_whitespace_no_sourcemap(&source, language, cfg).map_err(diagnostic)?;
+        (code, None)
+    };
+
+    let changed = code != source;
+
+    if args.diff && changed {
+        print!("{}", render_diff(path, &source, &code));
+    }
+    if args.check && changed {
+        eprintln!("would strip: {}", path.display());
+    }
+
+    if args.write && changed {
+        fs::write(path, &code)?;
+        if let Some(map) = map {
+            fs::write(format!("{}.map", path.display()), map)?;
+        }
+    } else if args.prints_code() {
+        print!("{code}");
+    }
+
+    Ok(changed)
+}
+
+/// Renders a unified diff between the original and stripped contents of `path`.
+fn render_diff(path: &Path, before: &str, after: &str) -> String {
+    let name = path.display().to_string();
+    TextDiff::from_lines(before, after)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{name}"), &format!("b/{name}"))
+        .to_string()
+}
+
+/// Expands the command-line paths into a deduplicated list of files.
+///
+/// - Existing files are taken as-is (their language is resolved later).
+/// - Directories are walked recursively for files with a supported extension, skipping hidden
+///   entries, `node_modules` and symlinked directories.
+/// - Anything else is treated as a glob pattern, which must match at least one file.
+fn collect_files(paths: &[String]) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for raw in paths {
+        let path = Path::new(raw);
+        if path.is_file() {
+            files.push(path.to_path_buf());
+        } else if path.is_dir() {
+            walk_dir(path, &mut files)?;
+        } else {
+            let before = files.len();
+            for entry in glob::glob(raw)? {
+                let entry = entry?;
+                if entry.is_file() {
+                    files.push(entry);
+                }
+            }
+            if files.len() == before {
+                return Err(format!("no files matched: {raw}").into());
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    files.retain(|f| seen.insert(f.clone()));
+    Ok(files)
+}
+
+/// Recursively collects files with a supported extension under `dir`, in sorted order.
+fn walk_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let name = entry.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if name.starts_with('.') || name == "node_modules" {
+            continue;
+        }
+        // Symlinked directories are not followed, so a link back up the tree cannot loop.
+        if fs::symlink_metadata(&entry)?.is_dir() {
+            walk_dir(&entry, files)?;
+        } else if entry.is_file()
+            && entry
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(Language::from_extension)
+                .is_some()
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates an empty scratch directory unique to the given test name.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "strip-whitespace-cli-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn collect_files_walks_directories_and_globs() {
+        let dir = scratch_dir("collect");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::create_dir_all(dir.join("node_modules")).unwrap();
+        fs::write(dir.join("a.astro"), "").unwrap();
+        fs::write(dir.join("nested/b.svelte"), "").unwrap();
+        fs::write(dir.join("nested/c.txt"), "").unwrap();
+        fs::write(dir.join("node_modules/d.vue"), "").unwrap();
+
+        let files = collect_files(&[dir.display().to_string()]).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.join("a.astro"), dir.join("nested/b.svelte")]
+        );
+
+        let pattern = format!("{}/**/*.svelte", dir.display());
+        let files = collect_files(&[pattern, dir.join("a.astro").display().to_string()]).unwrap();
+        assert_eq!(
+            files,
+            vec![dir.join("nested/b.svelte"), dir.join("a.astro")]
+        );
+
+        let missing = format!("{}/*.html", dir.display());
+        assert!(collect_files(&[missing]).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn collect_files_skips_symlinked_directories() {
+        let dir = scratch_dir("symlinks");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("nested/a.astro"), "").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("nested/loop")).unwrap();
+        std::os::unix::fs::symlink(dir.join("nested/a.astro"), dir.join("b.astro")).unwrap();
+
+        let files = collect_files(&[dir.display().to_string()]).unwrap();
+        assert_eq!(files, vec![dir.join("b.astro"), dir.join("nested/a.astro")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn render_diff_produces_unified_diff() {
+        let diff = render_diff(
+            Path::new("x.astro"),
+            "<div>\n  <p>hi</p>\n</div>\n",
+            "<div \n ><p>hi</p\n></div>\n",
+        );
+        assert!(diff.starts_with("--- a/x.astro\n+++ b/x.astro\n"));
+        assert!(diff.contains("-<div>\n"));
+        assert!(diff.contains("+<div \n"));
+    }
+
+    #[test]
+    fn tag_rules_parse_pattern_and_action() {
+        assert_eq!(
+            parse_tag_rule("Docs.*=strip-children"),
+            Ok(TagRule::new("Docs.*", TagAction::StripChildren))
+        );
+        assert_eq!(
+            parse_tag_rule("Trans=Collapse-To-Space"),
+            Ok(TagRule::new("Trans", TagAction::CollapseToSpace))
+        );
+        assert!(parse_tag_rule("Trans").is_err());
+        assert!(parse_tag_rule("Trans=drop").is_err());
+    }
+}
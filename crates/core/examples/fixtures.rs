@@ -25,6 +25,10 @@ fn detect_language(filename: &str) -> Option<Language> {
         Some(Language::Astro)
     } else if filename.ends_with(".svelte") {
         Some(Language::Svelte)
+    } else if filename.ends_with(".vue") {
+        Some(Language::Vue)
+    } else if filename.ends_with(".html") {
+        Some(Language::Html)
     } else {
         None
     }
@@ -35,6 +39,7 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let cfg = StripConfig {
         preserve_blank_lines: args.preserve_blank_lines,
+        ..Default::default()
     };
 
     // Read all files in the fixtures directory
@@ -7,6 +7,8 @@ use tree_sitter::Parser as TsParser;
 pub enum Language {
     Astro,
     Svelte,
+    Vue,
+    Html,
 }
 
 impl Language {
@@ -14,6 +16,8 @@ impl Language {
         match ext {
             "astro" => Some(Language::Astro),
             "svelte" => Some(Language::Svelte),
+            "vue" => Some(Language::Vue),
+            "html" => Some(Language::Html),
             _ => None,
         }
     }
@@ -60,6 +64,8 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let language_fn = match language {
         Language::Astro => tree_sitter_astro::LANGUAGE,
         Language::Svelte => tree_sitter_svelte_ng::LANGUAGE,
+        Language::Vue => tree_sitter_vue::LANGUAGE,
+        Language::Html => tree_sitter_html::LANGUAGE,
     };
     parser.set_language(&language_fn.into())?;
 
@@ -9,6 +9,8 @@ use strip_whitespace::{
 pub enum Language {
     Astro,
     Svelte,
+    Vue,
+    Html,
 }
 
 impl From<Language> for LanguageInner {
@@ -16,6 +18,8 @@ impl From<Language> for LanguageInner {
         match value {
             Language::Astro => LanguageInner::Astro,
             Language::Svelte => LanguageInner::Svelte,
+            Language::Vue => LanguageInner::Vue,
+            Language::Html => LanguageInner::Html,
         }
     }
 }
@@ -55,6 +59,8 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         match ext {
             "astro" => Language::Astro,
             "svelte" => Language::Svelte,
+            "vue" => Language::Vue,
+            "html" => Language::Html,
             _ => {
                 return Err(std::io::Error::other(format!(
                     "could not infer language from file extension: .{}",
@@ -73,6 +79,7 @@ fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let cfg = StripConfig {
         preserve_blank_lines: args.preserve_blank_lines,
+        ..Default::default()
     };
 
     let out_map_path = args.out_sourcemap.clone().or_else(|| {
@@ -0,0 +1,220 @@
+//! Source-positioned rendering of [`StripError`]s.
+//!
+//! A [`Diagnostic`] resolves an error's byte span (see [`StripError::span`]) to a 1-based line
+//! and UTF-16 column, the same column convention the emitted sourcemaps use, and keeps the text
+//! of the offending line so hosts can show it. [`Diagnostic::render`] formats it the way `rustc`
+//! does:
+//!
+//! ```text
+//! error: syntax error at line 2, column 6
+//!  --> src/Page.astro:2:6
+//!   |
+//! 2 | <div {>
+//!   |      ^
+//! ```
+//!
+//! Errors without a position (e.g. [`StripError::Timeout`]) point at the start of the file and
+//! carry no snippet.
+
+use std::fmt;
+
+use crate::{StripError, edit::compute_line_starts, utf16::Utf16Index};
+
+/// A [`StripError`] resolved against the source it was raised for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The error message, without position information.
+    pub message: String,
+    /// The name the source is displayed under, e.g. its path.
+    pub file_name: String,
+    /// 1-based line of the error.
+    pub line: usize,
+    /// 1-based column of the error, in UTF-16 code units.
+    pub column: usize,
+    /// Text of the offending line, without its terminator. Empty if the error has no position.
+    pub snippet: String,
+    /// Number of characters of `snippet` covered by the error, starting at `column` (at least 1).
+    pub len: usize,
+    has_span: bool,
+}
+
+impl Diagnostic {
+    /// Resolves `error` against `source`, which is displayed as `file_name`.
+    pub fn new(error: &StripError, source: &str, file_name: &str) -> Self {
+        let message = match error {
+            // The position is rendered separately, so keep only the reason.
+            StripError::InvalidEdit { message, .. } => format!("invalid edit: {message}"),
+            _ => error.to_string(),
+        };
+
+        let Some(span) = error.span() else {
+            return Self {
+                message,
+                file_name: file_name.to_string(),
+                line: 1,
+                column: 1,
+                snippet: String::new(),
+                len: 1,
+                has_span: false,
+            };
+        };
+
+        let line_starts = compute_line_starts(source);
+        let index = Utf16Index::new(source, &line_starts);
+        let start = floor_char_boundary(source, span.start.min(source.len()));
+        let (line0, col16) = index.byte_to_line_utf16_col(start);
+
+        let line_start = line_starts[line0];
+        let mut line_end = line_starts
+            .get(line0 + 1)
+            .map_or(source.len(), |&next| next - 1);
+        if source[line_start..line_end].ends_with('\r') {
+            line_end -= 1;
+        }
+        let start = start.clamp(line_start, line_end);
+        let end = floor_char_boundary(source, span.end.clamp(start, line_end));
+
+        Self {
+            message,
+            file_name: file_name.to_string(),
+            line: line0 + 1,
+            column: col16 + 1,
+            snippet: source[line_start..line_end].to_string(),
+            len: source[start..end].chars().count().max(1),
+            has_span: true,
+        }
+    }
+
+    /// Renders the diagnostic as multi-line, `rustc`-style text (without a trailing newline).
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "error: {}\n --> {}:{}:{}",
+            self.message, self.file_name, self.line, self.column
+        );
+        if !self.has_span {
+            return out;
+        }
+
+        let gutter = self.line.to_string();
+        let pad = " ".repeat(gutter.len());
+        // Keep tabs so the carets line up with the snippet however tabs are displayed.
+        let indent: String = self
+            .snippet
+            .chars()
+            .scan(0, |units, c| {
+                *units += c.len_utf16();
+                (*units < self.column).then_some(c)
+            })
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        out.push_str(&format!(
+            "\n{pad} |\n{gutter} | {}\n{pad} | {indent}{}",
+            self.snippet,
+            "^".repeat(self.len)
+        ));
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+/// Returns the largest char boundary of `s` at or before `i`.
+fn floor_char_boundary(s: &str, mut i: usize) -> usize {
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Language, StripConfig, SyntaxErrorPolicy, strip_whitespace_no_sourcemap};
+
+    #[test]
+    fn syntax_error_renders_line_column_and_snippet() {
+        let src = "<div>\n  <p>ok</p>\n\t<\n</div>";
+        let config = StripConfig {
+            syntax_errors: SyntaxErrorPolicy::Strict,
+            ..Default::default()
+        };
+        let err = strip_whitespace_no_sourcemap(src, Language::Svelte, &config).unwrap_err();
+        let diag = Diagnostic::new(&err, src, "App.svelte");
+
+        assert_eq!(diag.line, 3);
+        assert_eq!(diag.snippet, "\t<");
+        assert!(diag.render().contains(" --> App.svelte:3:"));
+        assert!(diag.render().contains("\n3 | \t<\n  | \t"));
+    }
+
+    #[test]
+    fn syntax_error_message_uses_the_diagnostic_column() {
+        let src = "<div>\n  <p>é😀</p> <\n</div>";
+        let config = StripConfig {
+            syntax_errors: SyntaxErrorPolicy::Strict,
+            ..Default::default()
+        };
+        let err = strip_whitespace_no_sourcemap(src, Language::Html, &config).unwrap_err();
+        let diag = Diagnostic::new(&err, src, "index.html");
+
+        assert_eq!((diag.line, diag.column), (2, 14));
+        assert_eq!(err.to_string(), "syntax error at line 2, column 14");
+    }
+
+    #[test]
+    fn columns_count_utf16_units_and_carets_cover_the_span() {
+        let src = "é😀 ab\nxyz";
+        let err = StripError::OverlappingEdits {
+            a_start: 0,
+            a_end: 8,
+            b_start: 7,
+            b_end: 9,
+        };
+        let diag = Diagnostic::new(&err, src, "in.html");
+
+        // 'é' is one UTF-16 unit, '😀' two, ' ' one.
+        assert_eq!((diag.line, diag.column, diag.len), (1, 5, 2));
+        assert_eq!(
+            diag.render(),
+            "error: overlapping edits: [0,8) overlaps [7,9)\n --> in.html:1:5\n  |\n1 | é😀 ab\n  |    ^^"
+        );
+    }
+
+    #[test]
+    fn spans_are_clamped_to_their_line() {
+        let src = "ab\r\ncd";
+        let err = StripError::OverlappingEdits {
+            a_start: 0,
+            a_end: 1,
+            b_start: 1,
+            b_end: 6,
+        };
+        let diag = Diagnostic::new(&err, src, "in.vue");
+        assert_eq!((diag.line, diag.column, diag.len), (1, 2, 1));
+        assert_eq!(diag.snippet, "ab");
+
+        let err = StripError::InvalidEdit {
+            byte: 100,
+            message: "out of bounds".to_string(),
+        };
+        let diag = Diagnostic::new(&err, src, "in.vue");
+        assert_eq!((diag.line, diag.column), (2, 3));
+        assert_eq!(diag.message, "invalid edit: out of bounds");
+    }
+
+    #[test]
+    fn errors_without_a_span_point_at_the_file_start() {
+        let diag = Diagnostic::new(&StripError::Timeout, "<p></p>", "in.astro");
+        assert_eq!(
+            diag.render(),
+            "error: parsing exceeded the configured time budget\n --> in.astro:1:1"
+        );
+    }
+}
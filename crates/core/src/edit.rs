@@ -10,8 +10,8 @@
 //!   sourcemap consumers).
 //! - Each edit can carry a per-output-byte origin map (see [`Edit::output_byte_to_input_byte`])
 //!   so moved/rotated bytes map back to their original positions.
-//! - Sourcemaps can either be created from scratch for the stripped output or rewritten from
-//!   an existing input sourcemap.
+//! - Sourcemaps can either be created from scratch for the stripped output
+//!   ([`create_sourcemap`]) or rewritten from an existing input sourcemap ([`rewrite_sourcemap`]).
 //!
 //! Invariants:
 //!
@@ -25,7 +25,7 @@ use crate::{StripError, utf16::Utf16Index};
 /// This crate represents changes as edits over byte ranges in the input (pre-strip) code.
 /// Edits are required to be non-overlapping (enforced upstream in `strip.rs`) and are applied
 /// left-to-right for mapping math and right-to-left for actual text replacement.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Edit {
     /// Start byte offset (inclusive) in the input code.
     pub start: usize,
@@ -85,95 +85,165 @@ pub fn create_sourcemap(
     builder.add_source(source_filename);
     builder.set_source_contents(0, Some(input_code));
 
-    // Anchor each output line start.
-    for (out_line, &out_start) in out_line_starts.iter().enumerate() {
-        if out_start >= map_len {
-            break;
+    for out_byte in anchor_output_bytes(&out_line_starts, map_len, &edit_out_spans) {
+        add_anchor_create(
+            &mut builder,
+            &out_utf16,
+            &in_utf16,
+            &out_to_in,
+            out_byte,
+            source_filename,
+        );
+    }
+
+    let out_map = builder.into_sourcemap();
+    let mut buf: Vec<u8> = Vec::new();
+    out_map.to_writer(&mut buf)?;
+    Ok(String::from_utf8(buf).expect("sourcemap JSON must be utf-8"))
+}
+
+/// Rewrite an existing sourcemap so it maps `output_code` back to the original sources.
+///
+/// `input_map_json` must map `input_code` (the pre-strip code) to its original sources, e.g. the
+/// map emitted by an upstream preprocessor. The result maps the stripped output directly to
+/// those original sources, so the intermediate `input_code` never shows up in the chain.
+///
+/// Mapping model:
+///
+/// - Output positions are first mapped back to `input_code` exactly like [`create_sourcemap`]
+///   (including the extra anchors around moved delimiters).
+/// - Each such `input_code` position is then traced through `input_map_json` using the closest
+///   preceding token on the same line. Positions without such a token stay unmapped.
+/// - Every token of the input map is carried over to its new output position, unless the byte
+///   it points at was removed.
+///
+/// Sources, source contents and names are taken from the input map.
+pub fn rewrite_sourcemap(
+    input_code: &str,
+    output_code: &str,
+    input_map_json: &str,
+    edits: &[Edit],
+) -> Result<String, StripError> {
+    let input_map = sourcemap::SourceMap::from_slice(input_map_json.as_bytes())?;
+
+    let out_len = output_code.len();
+    let in_len = input_code.len();
+
+    let in_line_starts = compute_line_starts(input_code);
+    let out_line_starts = compute_line_starts(output_code);
+    let in_utf16 = Utf16Index::new(input_code, &in_line_starts);
+    let out_utf16 = Utf16Index::new(output_code, &out_line_starts);
+
+    let (edit_out_spans, expected_out_len) = compute_output_spans(in_len, edits);
+    let map_len = out_len.min(expected_out_len);
+    let out_to_in = build_output_to_input_map(in_len, map_len, &edit_out_spans);
+
+    let mut in_to_out: Vec<Option<usize>> = vec![None; in_len];
+    for (out_byte, maybe_in_byte) in out_to_in.iter().copied().enumerate() {
+        if let Some(in_byte) = maybe_in_byte {
+            in_to_out[in_byte] = Some(out_byte);
         }
+    }
 
-        match out_to_in[out_start] {
-            Some(in_byte) if in_byte < in_len => {
-                let (in_line, in_col) = in_utf16.byte_to_line_utf16_col(in_byte);
+    let mut builder = sourcemap::SourceMapBuilder::new(None);
+    for src_id in 0..input_map.get_source_count() {
+        let Some(source) = input_map.get_source(src_id) else {
+            continue;
+        };
+        let new_id = builder.add_source(source);
+        builder.set_source_contents(new_id, input_map.get_source_contents(src_id));
+    }
+
+    // Carry over the input map's own tokens.
+    for token in input_map.tokens() {
+        let Some(in_byte) = in_utf16
+            .line_utf16_col_to_byte(token.get_dst_line() as usize, token.get_dst_col() as usize)
+        else {
+            continue;
+        };
+        let Some(out_byte) = in_to_out.get(in_byte).copied().flatten() else {
+            continue;
+        };
+        let (out_line, out_col) = out_utf16.byte_to_line_utf16_col(out_byte);
+        match token.get_source() {
+            Some(source) => {
                 builder.add(
                     out_line as u32,
-                    0,
-                    in_line as u32,
-                    in_col as u32,
-                    Some(source_filename),
-                    None,
+                    out_col as u32,
+                    token.get_src_line(),
+                    token.get_src_col(),
+                    Some(source),
+                    token.get_name(),
                     false,
                 );
             }
-            _ => {
-                // Unmapped: inserted bytes, or mismatch beyond best-effort prefix.
-                builder.add(out_line as u32, 0, u32::MAX, u32::MAX, None, None, false);
+            None => {
+                builder.add(
+                    out_line as u32,
+                    out_col as u32,
+                    u32::MAX,
+                    u32::MAX,
+                    None,
+                    None,
+                    false,
+                );
             }
         }
     }
 
-    // Add extra anchors around moved delimiters so column mapping stays useful.
-    for (e, out_start, out_end) in edit_out_spans {
-        // Start of the edit segment.
-        if out_start < map_len {
-            add_anchor_create(
-                &mut builder,
-                &out_utf16,
-                &in_utf16,
-                &out_to_in,
-                out_start,
-                source_filename,
-            );
-        }
+    // Add the same anchors as `create_sourcemap`, traced through the input map.
+    for out_byte in anchor_output_bytes(&out_line_starts, map_len, &edit_out_spans) {
+        add_anchor_rewrite(
+            &mut builder,
+            &out_utf16,
+            &in_utf16,
+            &out_to_in,
+            &input_map,
+            out_byte,
+        );
+    }
 
-        // Beginning of the delimiter in the output (it ends up at the end of the segment).
-        if e.moved_delim_len > 0 && out_end >= e.moved_delim_len {
-            let out_delim = out_end - e.moved_delim_len;
-            if out_delim < map_len {
-                add_anchor_create(
-                    &mut builder,
-                    &out_utf16,
-                    &in_utf16,
-                    &out_to_in,
-                    out_delim,
-                    source_filename,
-                );
-            }
+    let out_map = builder.into_sourcemap();
+    let mut buf: Vec<u8> = Vec::new();
+    out_map.to_writer(&mut buf)?;
+    Ok(String::from_utf8(buf).expect("sourcemap JSON must be utf-8"))
+}
 
-            // For multi-byte delimiters (e.g. "-->"), also anchor the last byte so the
-            // trailing '>' doesn't inherit the mapping of the first '-'.
-            if e.moved_delim_len > 1 {
-                let out_delim_last = out_end.saturating_sub(1);
-                if out_delim_last < map_len {
-                    add_anchor_create(
-                        &mut builder,
-                        &out_utf16,
-                        &in_utf16,
-                        &out_to_in,
-                        out_delim_last,
-                        source_filename,
-                    );
-                }
-            }
+/// Apply `edits` to `input_code` and return the output code.
+///
+/// Edits are validated with [`validate_edits`] first, so they must be sorted by ascending
+/// `start` and non-overlapping. Bytes outside of edit ranges are copied unchanged.
+pub fn apply_edits(input_code: &str, edits: &[Edit]) -> Result<String, StripError> {
+    validate_edits(input_code.len(), edits)?;
+
+    let src = input_code.as_bytes();
+    let mut out = Vec::<u8>::with_capacity(src.len());
+    let mut cursor = 0usize;
+    for edit in edits {
+        if !input_code.is_char_boundary(edit.start) || !input_code.is_char_boundary(edit.end) {
+            return Err(StripError::InvalidEdit {
+                byte: edit.start,
+                message: "edit boundaries split a UTF-8 character".to_string(),
+            });
         }
 
-        // Boundary at the start of the next node (right after a moved delimiter).
-        // This prevents the moved delimiter's mapping span from covering the next token.
-        if e.moved_delim_len > 0 && out_end < map_len {
-            add_anchor_create(
-                &mut builder,
-                &out_utf16,
-                &in_utf16,
-                &out_to_in,
-                out_end,
-                source_filename,
-            );
+        // Copy unchanged bytes before the edit.
+        if cursor < edit.start {
+            out.extend_from_slice(&src[cursor..edit.start]);
         }
+
+        // Apply the edit replacement.
+        out.extend_from_slice(edit.replacement.as_bytes());
+        cursor = edit.end;
     }
 
-    let out_map = builder.into_sourcemap();
-    let mut buf: Vec<u8> = Vec::new();
-    out_map.to_writer(&mut buf)?;
-    Ok(String::from_utf8(buf).expect("sourcemap JSON must be utf-8"))
+    // Copy any remaining unchanged bytes after the last edit.
+    if cursor < src.len() {
+        out.extend_from_slice(&src[cursor..]);
+    }
+
+    // Replacements are `String`s spliced at char boundaries, so the output is valid UTF-8.
+    Ok(String::from_utf8(out).expect("edits are spliced at UTF-8 char boundaries"))
 }
 
 /// Validate edit invariants required by this module.
@@ -192,16 +262,22 @@ pub fn validate_edits(input_len: usize, edits: &[Edit]) -> Result<(), StripError
     let mut prev_end: usize = 0;
     for (idx, e) in edits.iter().enumerate() {
         if e.start > e.end {
-            return Err(StripError::InvalidEdit(format!(
-                "start > end at index {idx}: start={}, end={}",
-                e.start, e.end
-            )));
+            return Err(StripError::InvalidEdit {
+                byte: e.start,
+                message: format!(
+                    "start > end at index {idx}: start={}, end={}",
+                    e.start, e.end
+                ),
+            });
         }
         if e.end > input_len {
-            return Err(StripError::InvalidEdit(format!(
-                "edit out of bounds at index {idx}: end={} > input_len={}",
-                e.end, input_len
-            )));
+            return Err(StripError::InvalidEdit {
+                byte: e.start.min(input_len),
+                message: format!(
+                    "edit out of bounds at index {idx}: end={} > input_len={}",
+                    e.end, input_len
+                ),
+            });
         }
         if idx > 0 && e.start < prev_end {
             return Err(StripError::OverlappingEdits {
@@ -212,26 +288,35 @@ pub fn validate_edits(input_len: usize, edits: &[Edit]) -> Result<(), StripError
             });
         }
         if e.output_byte_to_input_byte.len() != e.replacement.len() {
-            return Err(StripError::InvalidEdit(format!(
-                "output_byte_to_input_byte length mismatch at index {idx}: map_len={}, replacement_len={}",
-                e.output_byte_to_input_byte.len(),
-                e.replacement.len()
-            )));
+            return Err(StripError::InvalidEdit {
+                byte: e.start,
+                message: format!(
+                    "output_byte_to_input_byte length mismatch at index {idx}: map_len={}, replacement_len={}",
+                    e.output_byte_to_input_byte.len(),
+                    e.replacement.len()
+                ),
+            });
         }
         if e.moved_delim_len > e.replacement.len() {
-            return Err(StripError::InvalidEdit(format!(
-                "moved_delim_len too large at index {idx}: moved_delim_len={} > replacement_len={}",
-                e.moved_delim_len,
-                e.replacement.len()
-            )));
+            return Err(StripError::InvalidEdit {
+                byte: e.start,
+                message: format!(
+                    "moved_delim_len too large at index {idx}: moved_delim_len={} > replacement_len={}",
+                    e.moved_delim_len,
+                    e.replacement.len()
+                ),
+            });
         }
         for (out_off, maybe_in_byte) in e.output_byte_to_input_byte.iter().copied().enumerate() {
             match maybe_in_byte {
                 Some(in_byte) if in_byte >= input_len => {
-                    return Err(StripError::InvalidEdit(format!(
-                        "mapped input byte out of bounds at index {idx}: out_off={out_off}, in_byte={} >= input_len={}",
-                        in_byte, input_len
-                    )));
+                    return Err(StripError::InvalidEdit {
+                        byte: e.start,
+                        message: format!(
+                            "mapped input byte out of bounds at index {idx}: out_off={out_off}, in_byte={} >= input_len={}",
+                            in_byte, input_len
+                        ),
+                    });
                 }
                 _ => {}
             }
@@ -246,7 +331,7 @@ pub fn validate_edits(input_len: usize, edits: &[Edit]) -> Result<(), StripError
 ///
 /// The returned vector always includes `0` (first line) and then `i+1` for every `\n` byte at
 /// index `i`.
-fn compute_line_starts(s: &str) -> Vec<usize> {
+pub(crate) fn compute_line_starts(s: &str) -> Vec<usize> {
     let mut starts = vec![0usize];
     for (i, b) in s.as_bytes().iter().copied().enumerate() {
         if b == b'\n' {
@@ -370,6 +455,112 @@ fn build_output_to_input_map(
     out_to_in
 }
 
+/// Collect the output byte offsets that receive explicit sourcemap anchors.
+///
+/// This includes the start of every output line plus, for each edit, its output start and the
+/// boundaries around a moved delimiter (so the delimiter's mapping span doesn't bleed into the
+/// next token). Edits that change the length (collapsed text whitespace) also anchor their output
+/// end, where the unchanged bytes resume. Offsets at or beyond `map_len` are skipped.
+fn anchor_output_bytes(
+    out_line_starts: &[usize],
+    map_len: usize,
+    edit_out_spans: &[(&Edit, usize, usize)],
+) -> Vec<usize> {
+    let mut anchors: Vec<usize> = Vec::with_capacity(out_line_starts.len() + edit_out_spans.len());
+
+    // Anchor each output line start.
+    for &out_start in out_line_starts {
+        if out_start >= map_len {
+            break;
+        }
+        anchors.push(out_start);
+    }
+
+    // Add extra anchors around moved delimiters so column mapping stays useful.
+    for &(e, out_start, out_end) in edit_out_spans {
+        // Start of the edit segment.
+        if out_start < map_len {
+            anchors.push(out_start);
+        }
+
+        // Beginning of the delimiter in the output (it ends up at the end of the segment).
+        if e.moved_delim_len > 0 && out_end >= e.moved_delim_len {
+            let out_delim = out_end - e.moved_delim_len;
+            if out_delim < map_len {
+                anchors.push(out_delim);
+            }
+
+            // For multi-byte delimiters (e.g. "-->"), also anchor the last byte so the
+            // trailing '>' doesn't inherit the mapping of the first '-'.
+            if e.moved_delim_len > 1 {
+                let out_delim_last = out_end.saturating_sub(1);
+                if out_delim_last < map_len {
+                    anchors.push(out_delim_last);
+                }
+            }
+        }
+
+        // Boundary at the start of the next node (right after a moved delimiter).
+        // This prevents the moved delimiter's mapping span from covering the next token.
+        // After a shortening edit, the following bytes no longer line up with the edit's origin.
+        let resized = e.replacement.len() != e.end - e.start;
+        if (e.moved_delim_len > 0 || resized) && out_end < map_len {
+            anchors.push(out_end);
+        }
+    }
+
+    anchors
+}
+
+/// Add a single mapping entry for [`rewrite_sourcemap`].
+///
+/// The output byte is mapped back to `input_code` and then traced through `input_map`. If either
+/// step fails, this emits an explicit unmapped token entry.
+fn add_anchor_rewrite(
+    builder: &mut sourcemap::SourceMapBuilder,
+    out_utf16: &Utf16Index<'_>,
+    in_utf16: &Utf16Index<'_>,
+    out_to_in: &[Option<usize>],
+    input_map: &sourcemap::SourceMap,
+    out_byte: usize,
+) {
+    let (out_line, out_col) = out_utf16.byte_to_line_utf16_col(out_byte);
+    let original = out_to_in
+        .get(out_byte)
+        .copied()
+        .flatten()
+        .and_then(|in_byte| {
+            let (in_line, in_col) = in_utf16.byte_to_line_utf16_col(in_byte);
+            input_map
+                .lookup_token(in_line as u32, in_col as u32)
+                .filter(|t| t.get_dst_line() == in_line as u32)
+        });
+    match original {
+        Some(token) if token.get_source().is_some() => {
+            builder.add(
+                out_line as u32,
+                out_col as u32,
+                token.get_src_line(),
+                token.get_src_col(),
+                token.get_source(),
+                token.get_name(),
+                false,
+            );
+        }
+        _ => {
+            builder.add(
+                out_line as u32,
+                out_col as u32,
+                u32::MAX,
+                u32::MAX,
+                None,
+                None,
+                false,
+            );
+        }
+    }
+}
+
 /// Add a single mapping entry for [`create_sourcemap`].
 ///
 /// If `out_byte` is unmapped, this emits an explicit unmapped token entry.
@@ -596,6 +787,79 @@ mod tests {
         assert_eq!(t.get_src_col(), u32::MAX);
     }
 
+    /// Rewritten sourcemaps trace moved bytes through the input map to the original source.
+    #[test]
+    fn rewrite_sourcemap_traces_through_input_map() {
+        // The intermediate code "<a>\n<b>" was produced from lines 5 and 6 of "orig.svelte".
+        let input = "<a>\n<b>";
+        let mut input_builder = sourcemap::SourceMapBuilder::new(None);
+        for (line, len) in [(0u32, 3u32), (1, 3)] {
+            for col in 0..len {
+                input_builder.add(line, col, line + 5, col, Some("orig.svelte"), None, false);
+            }
+        }
+        let mut input_map_json: Vec<u8> = Vec::new();
+        input_builder
+            .into_sourcemap()
+            .to_writer(&mut input_map_json)
+            .unwrap();
+        let input_map_json = String::from_utf8(input_map_json).unwrap();
+
+        let edit = Edit {
+            start: 2,
+            end: 4,
+            replacement: "\n>".to_string(),
+            output_byte_to_input_byte: vec![Some(3), Some(2)],
+            moved_delim_len: 1,
+        };
+        let output = apply_single_edit(input, &edit);
+        assert_eq!(output, "<a\n><b>");
+
+        let sm_json = rewrite_sourcemap(input, &output, &input_map_json, &[edit]).unwrap();
+        let sm = sourcemap::SourceMap::from_slice(sm_json.as_bytes()).unwrap();
+        assert_eq!(sm.get_source(0), Some("orig.svelte"));
+
+        // The moved '>' maps to the original '>' on line 5.
+        let t_gt = sm.lookup_token(1, 0).expect("token for moved '>'");
+        assert_eq!(t_gt.get_source(), Some("orig.svelte"));
+        assert_eq!(t_gt.get_src_line(), 5);
+        assert_eq!(t_gt.get_src_col(), 2);
+
+        // The '<' of "<b>" maps to line 6 col 0.
+        let t_lt = sm.lookup_token(1, 1).expect("token for '<'");
+        assert_eq!(t_lt.get_source(), Some("orig.svelte"));
+        assert_eq!(t_lt.get_src_line(), 6);
+        assert_eq!(t_lt.get_src_col(), 0);
+
+        // Unchanged bytes keep their original mappings.
+        let t_a = sm.lookup_token(0, 1).expect("token for 'a'");
+        assert_eq!(t_a.get_src_line(), 5);
+        assert_eq!(t_a.get_src_col(), 1);
+    }
+
+    /// `apply_edits` copies unchanged bytes and splices replacements in order.
+    #[test]
+    fn apply_edits_splices_replacements() {
+        let input = "<a>\n<b>\n<c>";
+        let edits = vec![
+            Edit {
+                start: 2,
+                end: 4,
+                replacement: "\n>".to_string(),
+                output_byte_to_input_byte: vec![Some(3), Some(2)],
+                moved_delim_len: 1,
+            },
+            Edit {
+                start: 6,
+                end: 8,
+                replacement: "\n>".to_string(),
+                output_byte_to_input_byte: vec![Some(7), Some(6)],
+                moved_delim_len: 1,
+            },
+        ];
+        assert_eq!(apply_edits(input, &edits).unwrap(), "<a\n><b\n><c>");
+    }
+
     #[test]
     fn validate_edits_rejects_overlap() {
         let edits = vec![
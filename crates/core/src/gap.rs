@@ -0,0 +1,279 @@
+//! Strategies for rewriting the whitespace gap between two sibling nodes.
+//!
+//! Once a gap has passed the checks in [`strip`](crate::strip) (whitespace-only, not a preserved
+//! blank line, not trimmed or collapsed), a [`GapRewriter`] decides how to rewrite it. Pick one
+//! with [`StripConfig::gap_strategy`](crate::StripConfig::gap_strategy):
+//!
+//! - [`RotatingGapRewriter`] (the default) moves the whitespace inside a tag or expression, so
+//!   the next node keeps its line and column.
+//! - [`DeletingGapRewriter`] removes the whitespace, for pipelines that carry sourcemaps end to
+//!   end and don't need columns preserved.
+//! - Any other implementation can be plugged in with [`GapStrategy::Custom`].
+
+use std::{fmt, sync::Arc};
+
+use tree_sitter::Node;
+
+use crate::{Language, edit::Edit, strip::rotate_gap};
+
+/// Rewrites the whitespace gap between two sibling nodes.
+///
+/// Implementations must be `Send + Sync` so configs can be shared with worker threads.
+pub trait GapRewriter: fmt::Debug + Send + Sync {
+    /// Returns the edit for the gap between `prev` and `next`, or `None` to leave it untouched.
+    ///
+    /// `gap` is the whitespace right before `next`, usually
+    /// `source[prev.end_byte()..next.start_byte()]`. When
+    /// [`StripConfig::remove_comments`](crate::StripConfig::remove_comments) deletes comments
+    /// between the two nodes, it is only the whitespace after the last of them; an edit covering
+    /// `prev.end_byte()..next.start_byte()` deletes the comments as well. The
+    /// [`StripReport`](crate::StripReport) counts a comment as removed unless some output byte of
+    /// the edit maps back into it through
+    /// [`Edit::output_byte_to_input_byte`](crate::edit::Edit::output_byte_to_input_byte).
+    ///
+    /// The edit may extend into `prev` and `next` (e.g. to move a delimiter), but must not overlap
+    /// the edits of neighbouring gaps; overlaps fail with
+    /// [`StripError::OverlappingEdits`](crate::StripError::OverlappingEdits).
+    fn rewrite_gap(
+        &self,
+        source: &str,
+        prev: Node<'_>,
+        next: Node<'_>,
+        gap: &str,
+        language: Language,
+    ) -> Option<Edit>;
+}
+
+/// Moves a gap inside the previous node's trailing delimiter (`>`, `/>`, `-->`, `}`, `}}`), or
+/// after the next node's opener (`<tag`, `<!--`, `{`, `{{`) when the previous node is text.
+///
+/// Line breaks are kept, so following nodes keep their line and (mostly) their column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RotatingGapRewriter;
+
+impl GapRewriter for RotatingGapRewriter {
+    fn rewrite_gap(
+        &self,
+        source: &str,
+        prev: Node<'_>,
+        next: Node<'_>,
+        gap: &str,
+        language: Language,
+    ) -> Option<Edit> {
+        rotate_gap(source, prev, next, gap, language).map(|planned| planned.edit)
+    }
+}
+
+/// Deletes gaps outright. The output is smaller, but every following node moves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeletingGapRewriter;
+
+impl GapRewriter for DeletingGapRewriter {
+    fn rewrite_gap(
+        &self,
+        _source: &str,
+        prev: Node<'_>,
+        next: Node<'_>,
+        _gap: &str,
+        _language: Language,
+    ) -> Option<Edit> {
+        Some(Edit {
+            start: prev.end_byte(),
+            end: next.start_byte(),
+            replacement: String::new(),
+            output_byte_to_input_byte: Vec::new(),
+            moved_delim_len: 0,
+        })
+    }
+}
+
+/// The [`GapRewriter`] used by [`StripConfig::gap_strategy`](crate::StripConfig::gap_strategy).
+#[derive(Debug, Clone, Default)]
+pub enum GapStrategy {
+    /// Use [`RotatingGapRewriter`].
+    #[default]
+    Rotate,
+    /// Use [`DeletingGapRewriter`].
+    Delete,
+    /// Use a custom rewriter. Two custom strategies are equal if they share the same rewriter.
+    Custom(Arc<dyn GapRewriter>),
+}
+
+impl PartialEq for GapStrategy {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (GapStrategy::Rotate, GapStrategy::Rotate)
+            | (GapStrategy::Delete, GapStrategy::Delete) => true,
+            (GapStrategy::Custom(a), GapStrategy::Custom(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for GapStrategy {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StripConfig, strip_whitespace, strip_whitespace_no_sourcemap};
+
+    /// Deleting gaps removes the whitespace without moving any delimiter.
+    #[test]
+    fn deleting_strategy_removes_gaps() {
+        let src = "<div>\n  <p>\n    hi\n  </p>\n  {x}\n  <br/>\n</div>";
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Delete,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte] {
+            let res = strip_whitespace(src, "input", language, &config).unwrap();
+            assert_eq!(res.code, "<div><p>hi</p>{x}<br/></div>", "{language:?}");
+            assert_eq!(res.report.deleted_gaps, 6);
+            assert_eq!(res.report.delim_rotations.total(), 0);
+
+            // `{x}` moved from line 4 to line 1, and maps back to its original position.
+            let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+            let col = res.code.find('{').unwrap() as u32;
+            let token = sm.lookup_token(0, col).unwrap();
+            assert_eq!((token.get_src_line(), token.get_src_col()), (4, 2));
+        }
+    }
+
+    /// Rewrites every gap to a single line break.
+    #[derive(Debug)]
+    struct NewlineGaps;
+
+    impl GapRewriter for NewlineGaps {
+        fn rewrite_gap(
+            &self,
+            _source: &str,
+            prev: Node<'_>,
+            next: Node<'_>,
+            gap: &str,
+            _language: Language,
+        ) -> Option<Edit> {
+            (gap != "\n").then(|| Edit {
+                start: prev.end_byte(),
+                end: next.start_byte(),
+                replacement: "\n".to_string(),
+                output_byte_to_input_byte: vec![None],
+                moved_delim_len: 0,
+            })
+        }
+    }
+
+    /// Custom rewriters replace the rotations but not the checks that come before them.
+    #[test]
+    fn custom_rewriters_are_used_for_gaps() {
+        let src = "<div>\n  <p>a</p>\n\n  <pre>\n    b\n  </pre>\n</div>";
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(Arc::new(NewlineGaps)),
+            preserve_blank_lines: true,
+            ..Default::default()
+        };
+        let out = strip_whitespace_no_sourcemap(src, Language::Html, &config).unwrap();
+        assert_eq!(out, "<div>\n<p>a</p>\n\n  <pre>\n    b\n  </pre>\n</div>");
+
+        let same = config.clone();
+        assert_eq!(config, same);
+        assert_ne!(
+            config.gap_strategy,
+            GapStrategy::Custom(Arc::new(NewlineGaps))
+        );
+        assert_ne!(config.gap_strategy, GapStrategy::Rotate);
+    }
+
+    /// Removes the whitespace between two nodes but keeps everything else, e.g. comments.
+    #[derive(Debug)]
+    struct TrimmingGaps;
+
+    impl GapRewriter for TrimmingGaps {
+        fn rewrite_gap(
+            &self,
+            source: &str,
+            prev: Node<'_>,
+            next: Node<'_>,
+            _gap: &str,
+            _language: Language,
+        ) -> Option<Edit> {
+            let (start, end) = (prev.end_byte(), next.start_byte());
+            let region = &source[start..end];
+            let kept = region.trim_start();
+            let offset = start + region.len() - kept.len();
+            let kept = kept.trim_end();
+            Some(Edit {
+                start,
+                end,
+                replacement: kept.to_string(),
+                output_byte_to_input_byte: (offset..offset + kept.len()).map(Some).collect(),
+                moved_delim_len: 0,
+            })
+        }
+    }
+
+    /// Replaces every gap with a new, unmapped comment.
+    #[derive(Debug)]
+    struct MarkingGaps;
+
+    impl GapRewriter for MarkingGaps {
+        fn rewrite_gap(
+            &self,
+            _source: &str,
+            prev: Node<'_>,
+            next: Node<'_>,
+            _gap: &str,
+            _language: Language,
+        ) -> Option<Edit> {
+            let replacement = "<!-- gap -->".to_string();
+            Some(Edit {
+                start: prev.end_byte(),
+                end: next.start_byte(),
+                output_byte_to_input_byte: vec![None; replacement.len()],
+                replacement,
+                moved_delim_len: 0,
+            })
+        }
+    }
+
+    /// Custom rewrites are counted as such, with the comments they actually delete.
+    #[test]
+    fn report_counts_custom_rewrites() {
+        let src = "<p>a</p>\n<!-- x -->\n  <p>b</p>\n<p>c</p>";
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(Arc::new(NewlineGaps)),
+            remove_comments: true,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &config).unwrap();
+        assert_eq!(res.code, "<p>a</p>\n<p>b</p>\n<p>c</p>");
+        assert_eq!(
+            (res.report.custom_gaps, res.report.removed_comments),
+            (1, 1)
+        );
+        assert_eq!(res.report.prefix_rotations + res.report.deleted_gaps, 0);
+        assert_eq!(res.report.estimated_bytes_saved, src.len() - res.code.len());
+
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(Arc::new(TrimmingGaps)),
+            ..config
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &config).unwrap();
+        assert_eq!(res.code, "<p>a</p><!-- x --><p>b</p><p>c</p>");
+        assert_eq!(
+            (res.report.custom_gaps, res.report.removed_comments),
+            (2, 0)
+        );
+
+        // A new comment in the replacement does not keep the removed one.
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(Arc::new(MarkingGaps)),
+            ..config
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &config).unwrap();
+        assert_eq!(res.code, "<p>a</p><!-- gap --><p>b</p><!-- gap --><p>c</p>");
+        assert_eq!(
+            (res.report.custom_gaps, res.report.removed_comments),
+            (2, 1)
+        );
+    }
+}
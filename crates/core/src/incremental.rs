@@ -0,0 +1,622 @@
+//! Incremental re-stripping for sources that change a little at a time (e.g. dev-server saves).
+//!
+//! A [`StripSession`] keeps the previous source, its tree and its edits. When the source changes,
+//! the old tree is updated with [`tree_sitter::Tree::edit`] and reparsed, and only the edits of
+//! the container enclosing the changed bytes are recomputed; all other edits are shifted into
+//! place. The result is identical to a full run.
+//!
+//! Region selection:
+//!
+//! - The dirty range is the union of the edited byte ranges and the ranges tree-sitter reports as
+//!   structurally changed.
+//! - The region is the smallest container enclosing the dirty range, widened while it shares a
+//!   boundary with its parent container.
+//! - Edits touching the region are recomputed: every edit inside it, plus the parent container's
+//!   gap edits next to it (those rotate the region's first/last tokens).
+//!
+//! Fallbacks to a full run:
+//!
+//! - Comment directives can protect ranges far away from the comment, so sources containing one
+//!   are recomputed in full.
+//! - Error recovery can differ between incremental and fresh parses, so if the old or new tree
+//!   contains `ERROR`/`MISSING` nodes the source is parsed from scratch.
+//! - A changed [`StripConfig`] invalidates every edit.
+//! - Tag rules ([`StripConfig::tag_rules`]) reach outside the element they match (its
+//!   surrounding gaps, its whole subtree), so configs with rules are always recomputed in full.
+//! - Svelte's `<svelte:head>` changes how its whole subtree is stripped, so Svelte sources
+//!   containing one are recomputed in full as well.
+
+use std::ops::Range;
+
+use tree_sitter::{InputEdit, Node, Point, Tree};
+
+use crate::{
+    Language, StripError,
+    edit::{Edit, apply_edits, create_sourcemap, validate_edits},
+    parse::Parsers,
+    report::StripReport,
+    strip::{
+        StripConfig, SubtreeMode, SyntaxErrorPolicy, check_syntax_errors, collect_edits,
+        is_container, is_opaque_element, may_contain_svelte_head, may_suppress_stripping,
+        process_container_gaps, verify_protected_spans,
+    },
+};
+
+/// A text replacement applied to a session's source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChange {
+    /// Byte range replaced, in the source as it is before this change.
+    pub range: Range<usize>,
+    /// Replacement text.
+    pub text: String,
+}
+
+impl TextChange {
+    /// Creates a change replacing `range` with `text`.
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self {
+        Self {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// State kept between incremental runs over the same document.
+///
+/// Create one with [`Stripper::start_session`](crate::Stripper::start_session) and update it with
+/// [`Stripper::update_session`](crate::Stripper::update_session) or
+/// [`Stripper::update_session_source`](crate::Stripper::update_session_source).
+#[derive(Debug, Clone)]
+pub struct StripSession {
+    language: Language,
+    config: StripConfig,
+    source: String,
+    tree: Tree,
+    edits: Vec<Edit>,
+}
+
+impl StripSession {
+    /// The session's template language.
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    /// The current source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The tree for the current source.
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// The edits for the current source, sorted and non-overlapping.
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// The stripped code for the current source.
+    pub fn code(&self) -> Result<String, StripError> {
+        apply_edits(&self.source, &self.edits)
+    }
+
+    /// A brand-new sourcemap mapping [`StripSession::code`] back to the current source.
+    pub fn sourcemap(&self, source_filename: &str) -> Result<String, StripError> {
+        let code = self.code()?;
+        create_sourcemap(&self.source, &code, source_filename, &self.edits)
+    }
+}
+
+/// Runs a full strip of `source` and returns a session for it.
+pub(crate) fn start(
+    parsers: &mut Parsers,
+    source: &str,
+    language: Language,
+    config: &StripConfig,
+) -> Result<StripSession, StripError> {
+    parsers.set_timeout(config.parse_timeout);
+    let tree = parsers.parse(source, language)?;
+    let edits = full_edits(source, &tree, language, config)?;
+    Ok(StripSession {
+        language,
+        config: config.clone(),
+        source: source.to_string(),
+        tree,
+        edits,
+    })
+}
+
+/// Applies `changes` (in order) to `session` and recomputes its edits.
+///
+/// On error the session is left unchanged.
+pub(crate) fn update(
+    parsers: &mut Parsers,
+    session: &mut StripSession,
+    changes: &[TextChange],
+    config: &StripConfig,
+) -> Result<(), StripError> {
+    let language = session.language;
+    let mut source = session.source.clone();
+    let mut tree = session.tree.clone();
+    let mut edits = session.edits.clone();
+    let mut dirty: Option<Range<usize>> = None;
+
+    for change in changes {
+        let Range { start, end } = change.range.clone();
+        if start > end
+            || end > source.len()
+            || !source.is_char_boundary(start)
+            || !source.is_char_boundary(end)
+        {
+            return Err(StripError::InvalidEdit {
+                byte: start.min(source.len()),
+                message: format!(
+                    "text change range {start}..{end} is out of bounds or not on a char boundary"
+                ),
+            });
+        }
+
+        let start_position = point_at(&source, start);
+        let old_end_position = point_at(&source, end);
+        source.replace_range(start..end, &change.text);
+        let new_end = start + change.text.len();
+        tree.edit(&InputEdit {
+            start_byte: start,
+            old_end_byte: end,
+            new_end_byte: new_end,
+            start_position,
+            old_end_position,
+            new_end_position: point_at(&source, new_end),
+        });
+
+        edits.retain(|e| {
+            if start == end {
+                !(e.start < start && start < e.end)
+            } else {
+                !(e.start < end && start < e.end)
+            }
+        });
+        for e in edits.iter_mut().filter(|e| e.start >= end) {
+            e.start = e.start - end + new_end;
+            e.end = e.end - end + new_end;
+            for origin in e.output_byte_to_input_byte.iter_mut().flatten() {
+                *origin = *origin - end + new_end;
+            }
+        }
+
+        let shift = |pos: usize, inside: usize| {
+            if pos <= start {
+                pos
+            } else if pos >= end {
+                pos - end + new_end
+            } else {
+                inside
+            }
+        };
+        dirty = Some(match dirty {
+            Some(d) => shift(d.start, start).min(start)..shift(d.end, new_end).max(new_end),
+            None => start..new_end,
+        });
+    }
+
+    parsers.set_timeout(config.parse_timeout);
+    let mut new_tree = parsers.reparse(&source, language, &tree)?;
+
+    // Error recovery is not guaranteed to produce the same tree when reparsing incrementally, so
+    // sources with syntax errors are parsed from scratch to match a full run.
+    if tree.root_node().has_error() || new_tree.root_node().has_error() {
+        new_tree = parsers.parse(&source, language)?;
+    }
+
+    let edits = if *config != session.config
+        || !config.tag_rules.is_empty()
+        || new_tree.root_node().has_error()
+        || may_suppress_stripping(&session.source)
+        || may_suppress_stripping(&source)
+        || may_contain_svelte_head(&session.source, language)
+        || may_contain_svelte_head(&source, language)
+    {
+        full_edits(&source, &new_tree, language, config)?
+    } else {
+        check_syntax_errors(&source, new_tree.root_node(), config)?;
+        for r in tree.changed_ranges(&new_tree) {
+            dirty = Some(match dirty {
+                Some(d) => d.start.min(r.start_byte)..d.end.max(r.end_byte),
+                None => r.start_byte..r.end_byte,
+            });
+        }
+        if let Some(dirty) = dirty {
+            let (region, fresh) =
+                recompute_region(&source, new_tree.root_node(), dirty, language, config);
+            edits.retain(|e| e.end < region.start || e.start > region.end);
+            edits.extend(fresh);
+            edits.sort_by(|a, b| a.start.cmp(&b.start).then(a.end.cmp(&b.end)));
+        }
+        verify_protected_spans(new_tree.root_node(), &mut edits, |e| e, config)?;
+        validate_edits(source.len(), &edits)?;
+        edits
+    };
+
+    session.config = config.clone();
+    session.source = source;
+    session.tree = new_tree;
+    session.edits = edits;
+    Ok(())
+}
+
+/// Computes a single change turning `old` into `new` by trimming their common prefix and suffix.
+pub(crate) fn diff_sources(old: &str, new: &str) -> Option<TextChange> {
+    if old == new {
+        return None;
+    }
+
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+
+    let max_suffix = old.len().min(new.len()) - prefix;
+    let mut suffix = old
+        .bytes()
+        .rev()
+        .zip(new.bytes().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) {
+        suffix -= 1;
+    }
+
+    Some(TextChange::new(
+        prefix..old.len() - suffix,
+        &new[prefix..new.len() - suffix],
+    ))
+}
+
+/// Computes all edits for `tree`, like a non-incremental run.
+fn full_edits(
+    source: &str,
+    tree: &Tree,
+    language: Language,
+    config: &StripConfig,
+) -> Result<Vec<Edit>, StripError> {
+    let root = tree.root_node();
+    check_syntax_errors(source, root, config)?;
+    let mut edits: Vec<Edit> =
+        collect_edits(source, root, language, config, &mut StripReport::default())
+            .into_iter()
+            .map(|planned| planned.edit)
+            .collect();
+    verify_protected_spans(root, &mut edits, |e| e, config)?;
+    validate_edits(source.len(), &edits)?;
+    Ok(edits)
+}
+
+/// Picks the region to recompute for `dirty` and returns its closed byte range and fresh edits.
+///
+/// The fresh edits are exactly the edits of a full run that touch the region.
+fn recompute_region(
+    source: &str,
+    root: Node<'_>,
+    dirty: Range<usize>,
+    language: Language,
+    config: &StripConfig,
+) -> (Range<usize>, Vec<Edit>) {
+    let mut region = root
+        .descendant_for_byte_range(dirty.start, dirty.end)
+        .unwrap_or(root);
+    while !is_container(region.kind(), language) {
+        match region.parent() {
+            Some(parent) => region = parent,
+            None => break,
+        }
+    }
+
+    // Edits of the parent container's gaps touch the region's boundaries; if the parent shares
+    // a boundary with the region, the grandparent's gap edits could touch it too.
+    let mut parent = container_ancestor(region, language);
+    while let Some(p) = parent
+        && (p.start_byte() == region.start_byte() || p.end_byte() == region.end_byte())
+    {
+        region = p;
+        parent = container_ancestor(region, language);
+    }
+
+    let range = region.start_byte()..region.end_byte();
+    let mut report = StripReport::default();
+
+    // A whitespace-sensitive or raw ancestor means the walk never reaches the region.
+    let mut ancestor = region.parent();
+    while let Some(a) = ancestor {
+        if is_opaque_element(source, a, language, config) {
+            return (range, Vec::new());
+        }
+        ancestor = a.parent();
+    }
+
+    let mut fresh: Vec<Edit> = collect_edits(source, region, language, config, &mut report)
+        .into_iter()
+        .map(|planned| planned.edit)
+        .collect();
+    if let Some(parent) = parent
+        && !is_opaque_element(source, parent, language, config)
+        && !(config.syntax_errors == SyntaxErrorPolicy::SkipErrorSubtrees && parent.has_error())
+    {
+        let mut parent_edits = Vec::new();
+        process_container_gaps(
+            source,
+            parent,
+            language,
+            config,
+            SubtreeMode::Strip,
+            &mut parent_edits,
+            &mut report,
+        );
+        fresh.extend(
+            parent_edits
+                .into_iter()
+                .map(|planned| planned.edit)
+                .filter(|e| e.end >= range.start && e.start <= range.end),
+        );
+    }
+
+    (range, fresh)
+}
+
+/// Returns the nearest strict ancestor of `node` that is a container.
+fn container_ancestor<'t>(node: Node<'t>, language: Language) -> Option<Node<'t>> {
+    let mut ancestor = node.parent();
+    while let Some(a) = ancestor {
+        if is_container(a.kind(), language) {
+            return Some(a);
+        }
+        ancestor = a.parent();
+    }
+    None
+}
+
+/// Returns the tree-sitter point (row, byte column) of `byte` in `source`.
+fn point_at(source: &str, byte: usize) -> Point {
+    let before = &source.as_bytes()[..byte];
+    let row = before.iter().filter(|&&b| b == b'\n').count();
+    let column = before
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(byte, |nl| byte - nl - 1);
+    Point { row, column }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gap::GapStrategy,
+        strip::{AttributeWhitespace, TagAction, TagRule, TextWhitespace, plan_edits},
+    };
+
+    /// Asserts that the session's edits match a full run over its current source.
+    fn assert_matches_full_run(session: &StripSession, config: &StripConfig) {
+        let expected = plan_edits(session.source(), session.language(), config).unwrap();
+        assert_eq!(
+            session.edits(),
+            expected.as_slice(),
+            "{:?}",
+            session.source()
+        );
+    }
+
+    #[test]
+    fn incremental_updates_match_full_runs() {
+        let rewriting_text = StripConfig {
+            text_whitespace: TextWhitespace::CollapseToNewline,
+            trim_block_text: true,
+            inline_safe: true,
+            attribute_whitespace: AttributeWhitespace::CollapseToSpace,
+            ..Default::default()
+        };
+        let deleting = StripConfig {
+            gap_strategy: GapStrategy::Delete,
+            remove_comments: true,
+            ..Default::default()
+        };
+        let mut parsers = Parsers::new();
+        let src = "<div>\n  <p>\n    hello\n  </p>\n  <ul>\n    <li>a</li>\n  </ul>\n</div>\n";
+        for (language, config) in [Language::Astro, Language::Svelte, Language::Html]
+            .into_iter()
+            .flat_map(|l| {
+                [
+                    (l, StripConfig::default()),
+                    (l, rewriting_text.clone()),
+                    (l, deleting.clone()),
+                ]
+            })
+        {
+            let mut session = start(&mut parsers, src, language, &config).unwrap();
+            assert_matches_full_run(&session, &config);
+
+            let steps: &[(&str, &str)] = &[
+                ("hello", "hello  \n world"),
+                ("hello  \n world", "hello world"),
+                ("<li>a</li>", "<li>a</li>\n    <li>b</li>"),
+                ("\n  <ul>", "\n\n  <ul>"),
+                ("<p>", "<section>"),
+                ("</p>", "</section>"),
+                ("  </ul>", "  </ul>\n  <br/>"),
+                ("<br/>", "<!-- c -->\n  <br/>"),
+                ("<!-- c -->", "<!-- c -->\n  <!-- d -->"),
+                ("<!-- c -->\n  <!-- d -->\n  ", ""),
+                ("<div>", "<div class=\"x\">"),
+                ("class=\"x\"", "class=\"x\"\n  id=\"y\""),
+                ("\n  <section>\n    hello world\n  </section>", ""),
+                ("<br/>", "</div>"),
+            ];
+            for &(from, to) in steps {
+                let start = session.source().find(from).unwrap();
+                let change = TextChange::new(start..start + from.len(), to);
+                update(&mut parsers, &mut session, &[change], &config).unwrap();
+                assert_matches_full_run(&session, &config);
+            }
+        }
+    }
+
+    #[test]
+    fn source_diffs_apply_as_single_changes() {
+        let config = StripConfig::default();
+        let mut parsers = Parsers::new();
+        let snapshots = [
+            "<div>\n  <span>é</span>\n</div>",
+            "<div>\n  <span>ü</span>\n  text\n</div>",
+            "<div>\n  text\n</div>",
+            "<div>\n  text\n  <!-- c -->\n</div>",
+            "<main>\n  <div>\n    text\n  </div>\n</main>",
+        ];
+        let mut session = start(&mut parsers, snapshots[0], Language::Astro, &config).unwrap();
+        for next in &snapshots[1..] {
+            let change = diff_sources(session.source(), next).unwrap();
+            update(&mut parsers, &mut session, &[change], &config).unwrap();
+            assert_eq!(session.source(), *next);
+            assert_matches_full_run(&session, &config);
+        }
+        assert_eq!(diff_sources("same", "same"), None);
+    }
+
+    #[test]
+    fn multiple_changes_apply_in_order() {
+        let config = StripConfig::default();
+        let mut parsers = Parsers::new();
+        let src = "<div>\n  <a>1</a>\n  <b>2</b>\n</div>";
+        let mut session = start(&mut parsers, src, Language::Html, &config).unwrap();
+        let changes = [
+            TextChange::new(9..10, "x"),
+            TextChange::new(0..0, "<hr/>\n"),
+        ];
+        update(&mut parsers, &mut session, &changes, &config).unwrap();
+        assert_eq!(
+            session.source(),
+            "<hr/>\n<div>\n  <x>1</a>\n  <b>2</b>\n</div>"
+        );
+        assert_matches_full_run(&session, &config);
+    }
+
+    #[test]
+    fn directives_and_config_changes_fall_back_to_full_runs() {
+        let mut parsers = Parsers::new();
+        let config = StripConfig::default();
+        let src = "<div>\n  <!-- strip-whitespace-ignore -->\n  <p>\n    a\n  </p>\n</div>";
+        let mut session = start(&mut parsers, src, Language::Astro, &config).unwrap();
+
+        let start_byte = src.find("a\n").unwrap();
+        let change = TextChange::new(start_byte..start_byte + 1, "b");
+        update(&mut parsers, &mut session, &[change], &config).unwrap();
+        assert_matches_full_run(&session, &config);
+
+        let preserving = StripConfig {
+            preserve_blank_lines: true,
+            ..Default::default()
+        };
+        update(&mut parsers, &mut session, &[], &preserving).unwrap();
+        assert_matches_full_run(&session, &preserving);
+    }
+
+    #[test]
+    fn framework_directives_and_markers_match_full_runs() {
+        let mut parsers = Parsers::new();
+        let config = StripConfig::default();
+        let cases = [
+            (
+                Language::Svelte,
+                [
+                    "<div>\n  <p>a</p>\n</div>",
+                    "<svelte:options preserveWhitespace />\n<div>\n  <p>a</p>\n</div>",
+                    "<svelte:options />\n<div>\n  <p>a</p>\n</div>",
+                ],
+            ),
+            (
+                Language::Astro,
+                [
+                    "<div>\n  <p>\n    a\n  </p>\n</div>",
+                    "<div>\n  <p is:raw>\n    a\n  </p>\n</div>",
+                    "<div>\n  <p is:raw>\n    a b\n  </p>\n</div>",
+                ],
+            ),
+            (
+                Language::Html,
+                [
+                    "<div>\n  <p>\n    a\n  </p>\n</div>",
+                    "<div data-strip-whitespace=\"off\">\n  <p>\n    a\n  </p>\n</div>",
+                    "<div data-strip-whitespace=\"on\">\n  <p>\n    a\n  </p>\n</div>",
+                ],
+            ),
+        ];
+        for (language, snapshots) in cases {
+            let mut session = start(&mut parsers, snapshots[0], language, &config).unwrap();
+            for next in &snapshots[1..] {
+                let change = diff_sources(session.source(), next).unwrap();
+                update(&mut parsers, &mut session, &[change], &config).unwrap();
+                assert_matches_full_run(&session, &config);
+            }
+        }
+    }
+
+    #[test]
+    fn tag_rules_match_full_runs() {
+        let mut parsers = Parsers::new();
+        let config = StripConfig {
+            tag_rules: vec![
+                TagRule::new("Code", TagAction::Preserve),
+                TagRule::new("Trans", TagAction::CollapseToSpace),
+            ],
+            ..Default::default()
+        };
+        let mut session = start(
+            &mut parsers,
+            "<div>\n  <p>\n    <b>a</b>\n  </p>\n</div>",
+            Language::Html,
+            &config,
+        )
+        .unwrap();
+        for next in [
+            "<div>\n  <Code>\n    <b>a</b>\n  </Code>\n</div>",
+            "<div>\n  <Trans>\n    <b>a</b>\n  </Trans>\n</div>",
+            "<div>\n  <Trans>\n    <b>a</b>\n    <i>b</i>\n  </Trans>\n</div>",
+        ] {
+            let change = diff_sources(session.source(), next).unwrap();
+            update(&mut parsers, &mut session, &[change], &config).unwrap();
+            assert_matches_full_run(&session, &config);
+        }
+    }
+
+    #[test]
+    fn sources_with_syntax_errors_match_full_runs() {
+        let config = StripConfig::default();
+        let mut parsers = Parsers::new();
+        let src = "<div>\n  <p>a</p>\n  <p>b</p>\n</div>";
+        let mut session = start(&mut parsers, src, Language::Html, &config).unwrap();
+        for next in [
+            "<div>\n  <p>a</p>\n  <p\n  <p>b</p>\n</div>",
+            "<div>\n  <p>a</p>\n  <p>\n  <p>b</p>\n</div>",
+            "<div>\n  <p>a</p>\n  <p>c</p>\n  <p>b</p>\n</div>",
+        ] {
+            let change = diff_sources(session.source(), next).unwrap();
+            update(&mut parsers, &mut session, &[change], &config).unwrap();
+            assert_matches_full_run(&session, &config);
+        }
+    }
+
+    #[test]
+    fn invalid_changes_leave_the_session_untouched() {
+        let config = StripConfig::default();
+        let mut parsers = Parsers::new();
+        let src = "<p>é</p>";
+        let mut session = start(&mut parsers, src, Language::Html, &config).unwrap();
+        let change = TextChange::new(4..5, "e");
+        let err = update(&mut parsers, &mut session, &[change], &config).unwrap_err();
+        assert!(matches!(err, StripError::InvalidEdit { byte: 4, .. }));
+        assert_eq!(session.source(), src);
+    }
+}
@@ -1,4 +1,4 @@
-//! Whitespace stripping library for template languages (Astro, Svelte, etc.).
+//! Whitespace stripping library for template languages (Astro, Svelte, Vue, HTML, etc.).
 //!
 //! This crate provides a whitespace stripper that focuses on removing inter-node
 //! whitespace gaps while keeping mappings predictable.
@@ -6,38 +6,95 @@
 //! Entry points:
 //!
 //! - [`strip`] contains the core Rust APIs for stripping and (re)mapping sourcemaps.
+//! - [`Stripper`] is a reusable stripper that owns its parsers and configuration.
+//! - [`incremental`] holds the session state for re-stripping a document after small changes.
 //!
 //! Internals:
 //!
+//! - [`diagnostic`] renders a [`StripError`] with its source position and snippet.
 //! - [`edit`] holds the edit model and sourcemap creation/rewriting helpers.
+//! - [`gap`] defines the pluggable strategies for rewriting a gap between sibling nodes.
 //! - [`parse`] contains the tree-sitter parsing logic.
+//! - [`report`] defines the per-run statistics returned alongside the output.
 //! - [`utf16`] provides UTF-16 column indexing support for sourcemaps.
 
+pub mod diagnostic;
 pub mod edit;
+pub mod gap;
+pub mod incremental;
 pub mod parse;
+pub mod report;
 pub mod strip;
+pub mod stripper;
 pub mod utf16;
 
-pub use strip::{CodeAndSourcemap, StripConfig, strip_whitespace, strip_whitespace_no_sourcemap};
+pub use diagnostic::Diagnostic;
+pub use edit::{Edit, apply_edits};
+pub use gap::{DeletingGapRewriter, GapRewriter, GapStrategy, RotatingGapRewriter};
+pub use incremental::{StripSession, TextChange};
+pub use report::{DelimRotations, StripReport, Suppressions};
+pub use strip::{
+    AttributeWhitespace, CodeAndSourcemap, ProtectedSpanPolicy, StripConfig, SyntaxErrorPolicy,
+    TagAction, TagRule, TextWhitespace, plan_edits, strip_whitespace,
+    strip_whitespace_no_sourcemap, strip_whitespace_with_input_map, strip_whitespace_with_report,
+};
+pub use stripper::Stripper;
 
 /// Supported template languages for whitespace stripping.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Language {
     Astro,
     Svelte,
+    /// Vue single-file components. Only the `<template>` block is rewritten.
+    Vue,
+    /// Plain HTML documents and partials.
+    Html,
 }
 
 /// Errors that can occur during stripping.
 #[derive(thiserror::Error, Debug)]
 pub enum StripError {
+    /// Tree-sitter produced no tree. Like [`StripError::Timeout`] and
+    /// [`StripError::Cancelled`], this has no position in the input.
     #[error("tree-sitter failed to parse input")]
     ParseFailed,
 
+    /// Parsing took longer than [`StripConfig::parse_timeout`].
+    #[error("parsing exceeded the configured time budget")]
+    Timeout,
+
+    /// Parsing was cancelled through the cancellation flag (see [`Stripper::set_cancellation_flag`]).
+    #[error("parsing was cancelled")]
+    Cancelled,
+
+    /// The input contains a syntax error and [`SyntaxErrorPolicy::Strict`] was requested.
+    ///
+    /// `line` and `column` are 1-based; `column` counts UTF-16 code units from the start of the
+    /// line, like [`Diagnostic::column`] and the emitted sourcemaps.
+    #[error("syntax error at line {line}, column {column}")]
+    SyntaxError {
+        byte: usize,
+        line: usize,
+        column: usize,
+    },
+
     #[error("unsupported language")]
     UnsupportedLanguage,
 
-    #[error("invalid edit: {0}")]
-    InvalidEdit(String),
+    /// An edit intersects Astro frontmatter or the contents of a `<script>`/`<style>` element
+    /// (`kind` names which) and [`ProtectedSpanPolicy::Strict`] was requested.
+    #[error("edit [{edit_start},{edit_end}) intersects protected {kind} [{span_start},{span_end})")]
+    ProtectedSpanEdited {
+        edit_start: usize,
+        edit_end: usize,
+        span_start: usize,
+        span_end: usize,
+        kind: &'static str,
+    },
+
+    /// An edit or text change is malformed; `byte` is the input offset it starts at.
+    #[error("invalid edit at byte {byte}: {message}")]
+    InvalidEdit { byte: usize, message: String },
 
     #[error("overlapping edits: [{a_start},{a_end}) overlaps [{b_start},{b_end})")]
     OverlappingEdits {
@@ -50,3 +107,26 @@ pub enum StripError {
     #[error("invalid sourcemap: {0}")]
     SourceMap(#[from] sourcemap::Error),
 }
+
+impl StripError {
+    /// Returns the input byte range the error points at, if it has one.
+    ///
+    /// Errors that are not about a place in the input ([`StripError::ParseFailed`],
+    /// [`StripError::Timeout`], [`StripError::Cancelled`], [`StripError::UnsupportedLanguage`]
+    /// and [`StripError::SourceMap`]) return `None`. Use [`Diagnostic::new`] to turn the span
+    /// into a line, column and snippet.
+    pub fn span(&self) -> Option<std::ops::Range<usize>> {
+        match *self {
+            StripError::SyntaxError { byte, .. } | StripError::InvalidEdit { byte, .. } => {
+                Some(byte..byte)
+            }
+            StripError::OverlappingEdits { b_start, b_end, .. } => Some(b_start..b_end),
+            StripError::ProtectedSpanEdited {
+                edit_start,
+                edit_end,
+                ..
+            } => Some(edit_start..edit_end),
+            _ => None,
+        }
+    }
+}
@@ -1,44 +1,169 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    ops::ControlFlow,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 
-use tree_sitter::Parser;
+use tree_sitter::{ParseOptions, Parser};
+use web_time::Instant;
 
 use crate::{Language, StripError};
 
-thread_local! {
-    /// Shared Tree-sitter parser instance. We reuse it to avoid reloading the language for each call.
-    static ASTRO_PARSER: RefCell<Parser> = {
-        let mut parser = Parser::new();
-        let language: tree_sitter::Language = tree_sitter_astro::LANGUAGE.into();
-        parser
-            .set_language(&language)
-            .expect("tree-sitter-astro language load failed");
-        RefCell::new(parser)
-    };
-
-    /// Shared Tree-sitter parser instance for Svelte files.
-    static SVELTE_PARSER: RefCell<Parser> = {
-        let mut parser = Parser::new();
-        parser
-            .set_language(&tree_sitter_svelte_ng::LANGUAGE.into())
-            .expect("tree-sitter-svelte language load failed");
-        RefCell::new(parser)
-    };
+/// A set of Tree-sitter parsers, one per [`Language`], created lazily on first use.
+///
+/// Reusing a parser avoids reloading the language for each call. A [`Stripper`](crate::Stripper)
+/// owns one of these; the free functions share a thread-local instance.
+///
+/// Parsing can be bounded by a timeout and a cancellation flag, both checked from tree-sitter's
+/// progress callback.
+#[derive(Default)]
+pub struct Parsers {
+    astro: Option<Parser>,
+    svelte: Option<Parser>,
+    vue: Option<Parser>,
+    html: Option<Parser>,
+    timeout: Option<Duration>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
 }
 
-/// Parse source code for the given language.
-pub fn parse(source: &str, language: Language) -> Result<tree_sitter::Tree, StripError> {
+impl Parsers {
+    /// Creates an empty set; parsers are created when a language is first used.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the parser for `language`, creating it if needed.
+    pub fn get_mut(&mut self, language: Language) -> &mut Parser {
+        let slot = match language {
+            Language::Astro => &mut self.astro,
+            Language::Svelte => &mut self.svelte,
+            Language::Vue => &mut self.vue,
+            Language::Html => &mut self.html,
+        };
+        slot.get_or_insert_with(|| new_parser(language))
+    }
+
+    /// Sets the maximum time a single parse may take before failing with
+    /// [`StripError::Timeout`]. `None` means no limit.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Sets a flag that, once set to `true`, makes parsing fail with [`StripError::Cancelled`].
+    ///
+    /// The flag is checked before and periodically during each parse; it is never reset here.
+    pub fn set_cancellation_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.cancellation_flag = flag;
+    }
+
+    /// Parse source code for the given language.
+    pub fn parse(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<tree_sitter::Tree, StripError> {
+        self.run(source, language, None)
+    }
+
+    /// Reparse `source` incrementally, reusing `old_tree`.
+    ///
+    /// `old_tree` must already reflect the text changes via [`tree_sitter::Tree::edit`].
+    pub fn reparse(
+        &mut self,
+        source: &str,
+        language: Language,
+        old_tree: &tree_sitter::Tree,
+    ) -> Result<tree_sitter::Tree, StripError> {
+        self.run(source, language, Some(old_tree))
+    }
+
+    /// Parses `source`, enforcing the timeout and cancellation flag if any are set.
+    fn run(
+        &mut self,
+        source: &str,
+        language: Language,
+        old_tree: Option<&tree_sitter::Tree>,
+    ) -> Result<tree_sitter::Tree, StripError> {
+        let timeout = self.timeout;
+        let flag = self.cancellation_flag.clone();
+        let is_cancelled = || flag.as_ref().is_some_and(|f| f.load(Ordering::Relaxed));
+        if is_cancelled() {
+            return Err(StripError::Cancelled);
+        }
+
+        let parser = self.get_mut(language);
+        if timeout.is_none() && flag.is_none() {
+            return parser
+                .parse(source, old_tree)
+                .ok_or(StripError::ParseFailed);
+        }
+
+        let started = Instant::now();
+        let mut halted = None;
+        let mut progress = |_: &tree_sitter::ParseState| {
+            if is_cancelled() {
+                halted = Some(StripError::Cancelled);
+                ControlFlow::Break(())
+            } else if timeout.is_some_and(|t| started.elapsed() >= t) {
+                halted = Some(StripError::Timeout);
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        let bytes = source.as_bytes();
+        let tree = parser.parse_with_options(
+            &mut |i, _| bytes.get(i..).unwrap_or_default(),
+            old_tree,
+            Some(ParseOptions::new().progress_callback(&mut progress)),
+        );
+
+        match tree {
+            Some(tree) => Ok(tree),
+            None => {
+                // A halted parse is resumed by the next call unless the parser is reset.
+                parser.reset();
+                Err(halted.unwrap_or(StripError::ParseFailed))
+            }
+        }
+    }
+}
+
+/// Creates a parser with the grammar for `language` loaded.
+fn new_parser(language: Language) -> Parser {
+    let mut parser = Parser::new();
     match language {
-        Language::Astro => ASTRO_PARSER
-            .with(|p| {
-                let mut parser = p.borrow_mut();
-                parser.parse(source, None)
-            })
-            .ok_or(StripError::ParseFailed),
-        Language::Svelte => SVELTE_PARSER
-            .with(|p| {
-                let mut parser = p.borrow_mut();
-                parser.parse(source, None)
-            })
-            .ok_or(StripError::ParseFailed),
+        Language::Astro => parser
+            .set_language(&tree_sitter_astro::LANGUAGE.into())
+            .expect("tree-sitter-astro language load failed"),
+        Language::Svelte => parser
+            .set_language(&tree_sitter_svelte_ng::LANGUAGE.into())
+            .expect("tree-sitter-svelte language load failed"),
+        Language::Vue => parser
+            .set_language(&tree_sitter_vue::LANGUAGE.into())
+            .expect("tree-sitter-vue language load failed"),
+        Language::Html => parser
+            .set_language(&tree_sitter_html::LANGUAGE.into())
+            .expect("tree-sitter-html language load failed"),
     }
+    parser
+}
+
+thread_local! {
+    /// Shared Tree-sitter parser instances used by the free functions.
+    static PARSERS: RefCell<Parsers> = RefCell::new(Parsers::new());
+}
+
+/// Runs `f` with this thread's shared parsers.
+pub(crate) fn with_thread_parsers<R>(f: impl FnOnce(&mut Parsers) -> R) -> R {
+    PARSERS.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Parse source code for the given language, using this thread's shared parsers.
+pub fn parse(source: &str, language: Language) -> Result<tree_sitter::Tree, StripError> {
+    with_thread_parsers(|parsers| parsers.parse(source, language))
 }
@@ -0,0 +1,207 @@
+//! Statistics describing what a stripping run did.
+//!
+//! The counters are collected while walking the CST (gaps examined/skipped) and from the final
+//! edit list (rotations and saved bytes), so edits dropped by comment directives are not counted
+//! as rotations. Each edit is tagged with an [`EditKind`] where it is created, so the final count
+//! never has to guess what an edit did from its bytes. Comments removed by a custom
+//! [`GapRewriter`](crate::GapRewriter) are those its edit maps no output bytes back into.
+
+use crate::{edit::Edit, strip::TrailingDelim};
+
+/// Per-file statistics about a stripping run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StripReport {
+    /// Whitespace-only gaps between sibling nodes that were considered for rewriting.
+    ///
+    /// This includes gaps that were then skipped or left alone because of a comment directive.
+    pub gaps_examined: usize,
+    /// Gaps rewritten by rotating a trailing delimiter right, by delimiter kind.
+    pub delim_rotations: DelimRotations,
+    /// Gaps rewritten by rotating an opener prefix (`<tag`, `<!--`, `{`, `{{`) left.
+    pub prefix_rotations: usize,
+    /// Gaps left untouched because they contain a blank line and
+    /// [`StripConfig::preserve_blank_lines`](crate::StripConfig::preserve_blank_lines) is set.
+    pub skipped_blank_lines: usize,
+    /// Whitespace runs inside text nodes collapsed because of
+    /// [`StripConfig::text_whitespace`](crate::StripConfig::text_whitespace).
+    pub collapsed_text_runs: usize,
+    /// Whitespace ranges next to block-level boundaries deleted because of
+    /// [`StripConfig::trim_block_text`](crate::StripConfig::trim_block_text).
+    pub trimmed_block_text: usize,
+    /// Gaps collapsed to a single space because of
+    /// [`StripConfig::inline_safe`](crate::StripConfig::inline_safe) or a
+    /// [`TagAction::CollapseToSpace`](crate::TagAction::CollapseToSpace) rule.
+    pub inline_gaps: usize,
+    /// `data-strip-whitespace` attributes removed because of
+    /// [`StripConfig::remove_marker_attributes`](crate::StripConfig::remove_marker_attributes).
+    pub removed_marker_attributes: usize,
+    /// Gaps between two nodes deleted outright, e.g. by
+    /// [`GapStrategy::Delete`](crate::GapStrategy::Delete).
+    pub deleted_gaps: usize,
+    /// Gaps rewritten by a [`GapStrategy::Custom`](crate::GapStrategy::Custom) rewriter.
+    pub custom_gaps: usize,
+    /// Whitespace runs between attributes collapsed because of
+    /// [`StripConfig::attribute_whitespace`](crate::StripConfig::attribute_whitespace).
+    pub collapsed_attribute_gaps: usize,
+    /// Comments deleted because of
+    /// [`StripConfig::remove_comments`](crate::StripConfig::remove_comments).
+    pub removed_comments: usize,
+    /// Directives that kept parts of the file from being stripped.
+    pub suppressions: Suppressions,
+    /// Estimated bytes saved once the output is minified or compiled.
+    ///
+    /// This is the number of whitespace bytes in rewritten gaps: after rotation they sit inside a
+    /// tag or expression, where HTML minifiers and framework compilers discard them instead of
+    /// emitting text nodes. Collapsed and trimmed text, deleted gaps, removed comments and marker
+    /// attributes, and custom rewrites add the bytes they remove from the stripped source.
+    pub estimated_bytes_saved: usize,
+}
+
+/// Number of trailing-delimiter rotations, by delimiter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DelimRotations {
+    /// `>` of a start/end tag.
+    pub gt: usize,
+    /// `/>` of a self-closing tag.
+    pub slash_gt: usize,
+    /// `-->` of a comment.
+    pub comment_end: usize,
+    /// `}` of an Astro/Svelte expression, or a Svelte block or `{@...}` tag.
+    pub r_brace: usize,
+    /// `}}` of a Vue interpolation.
+    pub double_r_brace: usize,
+}
+
+/// Signals that whitespace matters, found while walking the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Suppressions {
+    /// The file contains `<!-- strip-whitespace-ignore-file -->`, so nothing was stripped.
+    ///
+    /// The other counters of such a report stay at zero.
+    pub ignore_file: bool,
+    /// The Svelte file sets `<svelte:options preserveWhitespace />`, so nothing was stripped.
+    pub svelte_preserve_whitespace: bool,
+    /// Astro elements with the `is:raw` directive whose contents were left untouched.
+    pub astro_raw_elements: usize,
+}
+
+impl Suppressions {
+    /// Returns true if any directive suppressed stripping.
+    pub fn any(&self) -> bool {
+        self.ignore_file || self.svelte_preserve_whitespace || self.astro_raw_elements > 0
+    }
+}
+
+impl DelimRotations {
+    /// Total number of trailing-delimiter rotations.
+    pub fn total(&self) -> usize {
+        self.gt + self.slash_gt + self.comment_end + self.r_brace + self.double_r_brace
+    }
+}
+
+/// What a planned edit does, as decided where the edit is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EditKind {
+    /// Rotates the previous node's trailing delimiter over a gap.
+    DelimRotation(TrailingDelim),
+    /// Rotates the next node's opener prefix, `prefix_len` bytes long, over a gap.
+    PrefixRotation { prefix_len: usize },
+    /// Deletes a gap between two nodes.
+    DeletedGap,
+    /// Rewrites a gap with a custom [`GapRewriter`](crate::GapRewriter).
+    CustomGap,
+    /// Collapses a gap between inline neighbours to a single space.
+    InlineGap,
+    /// Deletes whitespace between text and a block-level boundary.
+    TrimmedBlockText,
+    /// Collapses a whitespace run inside a text node.
+    CollapsedTextRun,
+    /// Collapses the whitespace between two attributes.
+    CollapsedAttributeGap,
+    /// Removes a marker attribute.
+    RemovedMarkerAttribute,
+    /// Deletes removed comments and the whitespace on one side of them.
+    RemovedComments,
+}
+
+/// An edit collected while walking the CST, with what it does for the report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PlannedEdit {
+    pub(crate) edit: Edit,
+    pub(crate) kind: EditKind,
+    /// Number of removed comments the edit deletes.
+    pub(crate) comments: usize,
+}
+
+impl PlannedEdit {
+    /// Tags `edit` with `kind`.
+    pub(crate) fn new(edit: Edit, kind: EditKind) -> Self {
+        Self {
+            edit,
+            kind,
+            comments: 0,
+        }
+    }
+}
+
+impl StripReport {
+    /// Tallies rotations, rewrites and saved bytes from the final (sorted, filtered) edits.
+    pub(crate) fn record_edits(&mut self, edits: &[PlannedEdit]) {
+        for PlannedEdit {
+            edit,
+            kind,
+            comments,
+        } in edits
+        {
+            let len = edit.end - edit.start;
+            let removed = len.saturating_sub(edit.replacement.len());
+            self.removed_comments += comments;
+            self.estimated_bytes_saved += match *kind {
+                EditKind::DelimRotation(delim) => {
+                    let counter = match delim {
+                        TrailingDelim::Gt => &mut self.delim_rotations.gt,
+                        TrailingDelim::SlashGt => &mut self.delim_rotations.slash_gt,
+                        TrailingDelim::CommentEnd => &mut self.delim_rotations.comment_end,
+                        TrailingDelim::RBrace => &mut self.delim_rotations.r_brace,
+                        TrailingDelim::DoubleRBrace => &mut self.delim_rotations.double_r_brace,
+                    };
+                    *counter += 1;
+                    len - edit.moved_delim_len
+                }
+                EditKind::PrefixRotation { prefix_len } => {
+                    self.prefix_rotations += 1;
+                    len - prefix_len
+                }
+                EditKind::DeletedGap => {
+                    self.deleted_gaps += 1;
+                    len
+                }
+                EditKind::CustomGap => {
+                    self.custom_gaps += 1;
+                    removed
+                }
+                EditKind::InlineGap => {
+                    self.inline_gaps += 1;
+                    removed
+                }
+                EditKind::TrimmedBlockText => {
+                    self.trimmed_block_text += 1;
+                    len
+                }
+                EditKind::CollapsedTextRun => {
+                    self.collapsed_text_runs += 1;
+                    removed
+                }
+                EditKind::CollapsedAttributeGap => {
+                    self.collapsed_attribute_gaps += 1;
+                    removed
+                }
+                EditKind::RemovedMarkerAttribute => {
+                    self.removed_marker_attributes += 1;
+                    removed
+                }
+                EditKind::RemovedComments => len,
+            };
+        }
+    }
+}
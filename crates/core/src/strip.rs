@@ -1,6 +1,6 @@
-//! Astro whitespace stripping with optional sourcemap support.
+//! Astro/Svelte/Vue/HTML whitespace stripping with optional sourcemap support.
 //!
-//! This module rewrites whitespace-only gaps between CST nodes in Astro source. Instead of only
+//! This module rewrites whitespace-only gaps between CST nodes in template source. Instead of only
 //! deleting whitespace bytes (which collapses columns), it rotates small delimiter/prefix tokens
 //! across the gap to reduce column drift and keep edits predictable for mapping.
 //!
@@ -15,39 +15,401 @@
 //! Definitions:
 //!
 //! - A "gap" is the byte range between adjacent named children of container nodes (`document` and
-//!   `element`) where the range is entirely whitespace.
+//!   `element`, plus Svelte blocks and Vue `<template>` elements) where the range is entirely
+//!   whitespace.
 //!
 //! Transformations:
 //!
 //! 1. Rotate trailing delimiter right: if the previous node ends with a delimiter such as `>`,
-//!    `/>`, `-->`, or `}`, move that delimiter to the end of the gap (immediately before the next
-//!    node). Optionally move up to one indentation byte (two for `/>`) from the final line of the
-//!    gap to before the gap to reduce column shifts.
+//!    `/>`, `-->`, `}`, or `}}`, move that delimiter to the end of the gap (immediately before
+//!    the next node). Optionally move up to one indentation byte (two for `/>`) from the final
+//!    line of the gap to before the gap to reduce column shifts.
 //! 2. Rotate opener prefix left: if the previous node is `text` and the next node begins with an
-//!    opener prefix such as `<!--`, `{`, or `<tag`/`</tag`, move that prefix so it becomes
+//!    opener prefix such as `<!--`, `{`, `{{`, or `<tag`/`</tag`, move that prefix so it becomes
 //!    adjacent to the text and leave the whitespace after the prefix.
+//! 3. Collapse text whitespace (opt-in, see [`StripConfig::text_whitespace`]): each whitespace run
+//!    between two words of a `text` node becomes a single space, or a single line break if the run
+//!    contains one. The replacement maps back to the first byte of the run (or to the line break),
+//!    so sourcemaps stay accurate.
+//! 4. Trim block text (opt-in, see [`StripConfig::trim_block_text`]): whitespace between text and
+//!    the start or end of a block-level element, or a block-level sibling, is deleted instead of
+//!    rotated, since browsers do not render it there.
+//! 5. Inline-safe gaps (opt-in, see [`StripConfig::inline_safe`]): a gap between two inline
+//!    neighbours is collapsed to a single space instead of being rotated, since browsers render
+//!    it as one.
+//! 6. Attribute whitespace (opt-in, see [`StripConfig::attribute_whitespace`]): whitespace runs
+//!    between the tag name and attributes of a start tag collapse to a single space, or keep
+//!    their last line break and indentation. The replacement maps back to the bytes it keeps.
+//! 7. Remove comments (opt-in, see [`StripConfig::remove_comments`]): `comment` nodes are deleted
+//!    and the whitespace before and after them is treated as one gap, so it is rotated, trimmed
+//!    or collapsed by the same edit. Gaps that are left alone lose the comment and the
+//!    whitespace on one side of it.
+//!
+//! Transformations 1 and 2 are what the default [`GapStrategy`] does with a gap that none of the
+//! others apply to; [`StripConfig::gap_strategy`] can delete such gaps instead, or hand them to a
+//! custom [`GapRewriter`](crate::gap::GapRewriter).
 //!
 //! Notes:
 //!
 //! - Whitespace inside an `html_interpolation` node (the `{ ... }` expression) is not rewritten,
-//!   because it is part of JavaScript and can be semantically meaningful.
+//!   because it is part of JavaScript and can be semantically meaningful. The same applies to
+//!   Vue `{{ ... }}` interpolations.
+//! - For Vue, only the `<template>` block is rewritten; the root node is not a container, so
+//!   `<script>` and `<style>` blocks and the gaps between blocks are left alone.
+//! - For HTML, `script_element` and `style_element` take part in rotations like any element, but
+//!   their raw text content is never a container and is left untouched.
+//! - Once the edits are collected, none may intersect Astro frontmatter, `script`/`style`
+//!   contents or another `raw_text` node. Offending edits fail the run with
+//!   [`StripError::ProtectedSpanEdited`], or are dropped under [`ProtectedSpanPolicy::Lenient`].
+//! - HTML comment directives opt regions out of stripping:
+//!   `<!-- strip-whitespace-ignore -->` protects the next sibling node,
+//!   `<!-- strip-whitespace-disable -->` / `<!-- strip-whitespace-enable -->` protect everything
+//!   between them, and `<!-- strip-whitespace-ignore-file -->` disables stripping entirely.
+//!   Any edit that touches a protected range is dropped.
+//! - Framework directives are honoured too: Svelte's `<svelte:options preserveWhitespace />`
+//!   disables stripping for the file, and Astro's `is:raw` protects an element's contents like
+//!   `<pre>`. Both are recorded in [`StripReport::suppressions`].
+//! - A `data-strip-whitespace="off"` attribute ([`MARKER_ATTRIBUTE`]) switches stripping off for
+//!   an element's subtree, and `"on"` switches it back on for a nested subtree. Gaps around the
+//!   marked element still belong to its parent. The markers can be removed from the output with
+//!   [`StripConfig::remove_marker_attributes`].
+//! - [`StripConfig::tag_rules`] apply a [`TagAction`] to elements by tag or component name, for
+//!   components whose whitespace the built-in tables cannot know about.
+//! - Whitespace inside Svelte's `<svelte:head>` never renders, so its gaps are stripped even
+//!   where [`StripConfig::preserve_blank_lines`] or [`StripConfig::inline_safe`] would keep them.
+//!   Tag rules and markers take precedence. The other `<svelte:*>` elements follow the normal
+//!   element rules.
 //! - All offsets in this module are byte offsets (tree-sitter’s model). Edits carry a per-byte
 //!   origin map so sourcemaps can be created or rewritten.
 
+use std::{ops::Range, time::Duration};
+
 use crate::{
     Language, StripError,
-    edit::{Edit, create_sourcemap, validate_edits},
-    parse::parse,
+    edit::{Edit, apply_edits, create_sourcemap, rewrite_sourcemap, validate_edits},
+    gap::{DeletingGapRewriter, GapRewriter, GapStrategy},
+    parse::{Parsers, with_thread_parsers},
+    report::{EditKind, PlannedEdit, StripReport},
 };
 
+/// Tag names whose content is rendered with whitespace preserved by browsers.
+///
+/// This is the default value of [`StripConfig::whitespace_sensitive_elements`].
+pub const DEFAULT_WHITESPACE_SENSITIVE_ELEMENTS: &[&str] = &["pre", "textarea", "listing"];
+
+/// Attribute that switches stripping off (`"off"`) or back on (`"on"`) for an element's subtree.
+pub const MARKER_ATTRIBUTE: &str = "data-strip-whitespace";
+
+/// Tag names of HTML elements rendered as blocks, used by [`StripConfig::trim_block_text`].
+///
+/// Names are matched ASCII case-insensitively in HTML and exactly elsewhere, so `<Header>` in a
+/// component file is treated as inline unless it is listed in
+/// [`StripConfig::block_level_components`].
+pub const BLOCK_LEVEL_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hgroup",
+    "hr",
+    "html",
+    "legend",
+    "li",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "search",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
 /// Configuration options for whitespace stripping.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct StripConfig {
     /// If true, preserves "section breaks" by skipping gaps that contain an empty line.
     ///
     /// A "blank line" here is defined as two consecutive line breaks within the whitespace gap
     /// (either `\n\n` or `\r\n\r\n`).
     pub preserve_blank_lines: bool,
+    /// Tag names of elements whose descendants are never rewritten.
+    ///
+    /// Gaps around such an element are still stripped, but nothing between its start and end
+    /// tags is touched. Names are matched ASCII case-insensitively in HTML and exactly elsewhere,
+    /// where `<Pre>` is a component. Defaults to
+    /// [`DEFAULT_WHITESPACE_SENSITIVE_ELEMENTS`]; extend it for elements styled with
+    /// `white-space: pre` (e.g. `code`).
+    pub whitespace_sensitive_elements: Vec<String>,
+    /// How to handle inputs that tree-sitter could only parse with `ERROR` or `MISSING` nodes.
+    pub syntax_errors: SyntaxErrorPolicy,
+    /// What happens to edits that would change Astro frontmatter or the contents of a `<script>`
+    /// or `<style>` element. The built-in rules never produce them; the check guards against
+    /// unexpected parse trees and custom [`GapRewriter`](crate::GapRewriter)s.
+    pub protected_spans: ProtectedSpanPolicy,
+    /// Maximum time tree-sitter may spend parsing one input before failing with
+    /// [`StripError::Timeout`]. `None` (the default) means no limit.
+    pub parse_timeout: Option<Duration>,
+    /// How whitespace runs inside `text` nodes are rewritten. Defaults to
+    /// [`TextWhitespace::Preserve`], which only rewrites gaps between nodes.
+    pub text_whitespace: TextWhitespace,
+    /// If true, whitespace between text and a block-level boundary is deleted instead of rotated.
+    ///
+    /// A block-level boundary is the start or end tag of the enclosing element or a sibling
+    /// element listed in [`BLOCK_LEVEL_ELEMENTS`] or [`StripConfig::block_level_components`].
+    /// Whitespace at the edges of the text node itself is deleted too. Gaps next to inline
+    /// elements (e.g. `<span>`) are left to the rotations.
+    pub trim_block_text: bool,
+    /// If true, gaps between two inline neighbours are collapsed to a single space instead of
+    /// being rotated away, so the rendered output keeps the space (`<a>One</a> <a>Two</a>`).
+    ///
+    /// A neighbour is inline unless it is a block-level boundary as described for
+    /// [`StripConfig::trim_block_text`], or renders nothing in place (a doctype, frontmatter,
+    /// `<script>`, `<style>` or head metadata like `<meta>`); text, comments and expressions are
+    /// inline.
+    pub inline_safe: bool,
+    /// Component names rendered as blocks, in addition to [`BLOCK_LEVEL_ELEMENTS`].
+    ///
+    /// Names are matched exactly. Used by [`StripConfig::trim_block_text`] and
+    /// [`StripConfig::inline_safe`]; unlisted components are treated as inline.
+    pub block_level_components: Vec<String>,
+    /// If true, [`MARKER_ATTRIBUTE`] attributes are removed from the output, together with the
+    /// whitespace before them, so they don't ship to production HTML.
+    ///
+    /// Markers are only removed where the walk reaches them, i.e. not inside whitespace-sensitive
+    /// elements, `is:raw` elements or ranges protected by comment directives.
+    pub remove_marker_attributes: bool,
+    /// Per-tag overrides of how elements and the gaps around them are rewritten.
+    ///
+    /// The first rule whose pattern matches an element's tag name applies. Rules take precedence
+    /// over [`StripConfig::whitespace_sensitive_elements`] and `is:raw`; a
+    /// [`MARKER_ATTRIBUTE`] on the element takes precedence over its rule.
+    pub tag_rules: Vec<TagRule>,
+    /// How gaps that are not skipped, trimmed or collapsed are rewritten. Defaults to
+    /// [`GapStrategy::Rotate`], which preserves the columns of following nodes.
+    pub gap_strategy: GapStrategy,
+    /// How whitespace between the tag name and attributes of a start tag or self-closing tag is
+    /// rewritten. Defaults to [`AttributeWhitespace::Preserve`].
+    ///
+    /// Whitespace before the closing `>` or `/>` is left to the gap rotations. Tags inside
+    /// subtrees that are not stripped (e.g. `<pre>` and its start tag) are left untouched.
+    pub attribute_whitespace: AttributeWhitespace,
+    /// If true, `comment` nodes are deleted and the whitespace around them is stripped as one
+    /// gap.
+    ///
+    /// Comment directives, Svelte's `svelte-ignore` comments, conditional comments
+    /// (`<!--[if IE]>`) and license comments (`<!--! ... -->`) are kept. Comments inside subtrees
+    /// that are not stripped are kept too.
+    pub remove_comments: bool,
+}
+
+impl Default for StripConfig {
+    fn default() -> Self {
+        Self {
+            preserve_blank_lines: false,
+            whitespace_sensitive_elements: DEFAULT_WHITESPACE_SENSITIVE_ELEMENTS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            syntax_errors: SyntaxErrorPolicy::default(),
+            protected_spans: ProtectedSpanPolicy::default(),
+            parse_timeout: None,
+            text_whitespace: TextWhitespace::default(),
+            trim_block_text: false,
+            inline_safe: false,
+            block_level_components: Vec::new(),
+            remove_marker_attributes: false,
+            tag_rules: Vec::new(),
+            gap_strategy: GapStrategy::default(),
+            attribute_whitespace: AttributeWhitespace::default(),
+            remove_comments: false,
+        }
+    }
+}
+
+/// Policy for inputs containing syntax errors (`ERROR` or `MISSING` nodes).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyntaxErrorPolicy {
+    /// Fail with [`StripError::SyntaxError`] pointing at the first error.
+    Strict,
+    /// Strip normally, but leave every gap of a container that contains an error untouched.
+    SkipErrorSubtrees,
+    /// Strip around error nodes as if they were regular nodes.
+    #[default]
+    Lenient,
+}
+
+/// Policy for edits intersecting a protected span: Astro frontmatter, the contents of a
+/// `<script>` or `<style>` element, or any other `raw_text` node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProtectedSpanPolicy {
+    /// Fail with [`StripError::ProtectedSpanEdited`] pointing at the first offending edit.
+    #[default]
+    Strict,
+    /// Drop the offending edits and keep the rest.
+    Lenient,
+}
+
+/// How whitespace runs inside `text` nodes are rewritten.
+///
+/// Only runs between two non-whitespace characters of a text node are affected; only ASCII
+/// whitespace is collapsed, so non-breaking spaces are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextWhitespace {
+    /// Leave text nodes untouched.
+    #[default]
+    Preserve,
+    /// Collapse each run to a single space.
+    CollapseToSpace,
+    /// Collapse each run to a single line break (`\n`, or `\r\n` if the run has one) if it
+    /// contains one, and to a single space otherwise.
+    CollapseToNewline,
+}
+
+/// How whitespace between the attributes of a tag is rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AttributeWhitespace {
+    /// Leave tags untouched.
+    #[default]
+    Preserve,
+    /// Collapse each run to a single space: `<div\n  class="a"\n  id="b">` becomes
+    /// `<div class="a" id="b">`.
+    CollapseToSpace,
+    /// Collapse runs within a line to a single space, but keep the last line break of a run and
+    /// the indentation after it, so every attribute keeps its line and column.
+    KeepLineBreaks,
+}
+
+/// Maps elements whose tag name matches [`TagRule::pattern`] to a [`TagAction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagRule {
+    /// Tag or component name to match, ASCII case-insensitively in HTML and exactly elsewhere.
+    /// `*` matches any run of characters, e.g. `Icon*` or `*.Code`.
+    pub pattern: String,
+    /// What to do with matching elements.
+    pub action: TagAction,
+}
+
+impl TagRule {
+    /// Creates a rule applying `action` to elements whose tag name matches `pattern`.
+    pub fn new(pattern: impl Into<String>, action: TagAction) -> Self {
+        Self {
+            pattern: pattern.into(),
+            action,
+        }
+    }
+
+    /// Returns true if `tag_name` matches this rule's pattern.
+    fn matches(&self, tag_name: &str, language: Language) -> bool {
+        let eq = |a: u8, b: u8| match language {
+            Language::Html => a.eq_ignore_ascii_case(&b),
+            Language::Astro | Language::Svelte | Language::Vue => a == b,
+        };
+        let (pattern, name) = (self.pattern.as_bytes(), tag_name.as_bytes());
+        let (mut p, mut n) = (0, 0);
+        // Position of the last `*` and the name byte it currently extends to.
+        let mut star: Option<(usize, usize)> = None;
+        while n < name.len() {
+            if p < pattern.len() && pattern[p] == b'*' {
+                star = Some((p, n));
+                p += 1;
+            } else if p < pattern.len() && eq(pattern[p], name[n]) {
+                p += 1;
+                n += 1;
+            } else if let Some((star_p, star_n)) = star {
+                star = Some((star_p, star_n + 1));
+                p = star_p + 1;
+                n = star_n + 1;
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|&b| b == b'*')
+    }
+}
+
+/// How a [`TagRule`] rewrites the elements it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagAction {
+    /// Strip the element like any other, even if it is whitespace-sensitive or `is:raw`.
+    Strip,
+    /// Leave the element's contents and the gaps around it untouched.
+    Preserve,
+    /// Strip the element's contents, but leave the gaps around it untouched.
+    StripChildren,
+    /// Strip the gaps around the element, but leave its contents untouched (like `<pre>`).
+    StripOutside,
+    /// Collapse the gaps inside the element to a single space instead of rotating them, e.g. for
+    /// translation components whose children are joined into a sentence.
+    CollapseToSpace,
+}
+
+impl StripConfig {
+    /// Returns the action of the first [`TagRule`] matching `tag_name`.
+    fn tag_action(&self, tag_name: &str, language: Language) -> Option<TagAction> {
+        self.tag_rules
+            .iter()
+            .find(|rule| rule.matches(tag_name, language))
+            .map(|rule| rule.action)
+    }
+
+    /// Returns true if `tag_name` is listed in [`StripConfig::whitespace_sensitive_elements`].
+    fn is_whitespace_sensitive(&self, tag_name: &str, language: Language) -> bool {
+        self.whitespace_sensitive_elements
+            .iter()
+            .any(|name| tag_names_eq(name, tag_name, language))
+    }
+
+    /// Returns true if `tag_name` is listed in [`BLOCK_LEVEL_ELEMENTS`] or
+    /// [`StripConfig::block_level_components`].
+    fn is_block_level(&self, tag_name: &str, language: Language) -> bool {
+        BLOCK_LEVEL_ELEMENTS
+            .iter()
+            .any(|name| tag_names_eq(name, tag_name, language))
+            || self
+                .block_level_components
+                .iter()
+                .any(|name| name == tag_name)
+    }
+}
+
+/// Returns true if the tag names `a` and `b` are equal: ASCII case-insensitively in HTML, and
+/// exactly in Astro, Svelte and Vue, where a capitalized name is a component.
+fn tag_names_eq(a: &str, b: &str, language: Language) -> bool {
+    match language {
+        Language::Html => a.eq_ignore_ascii_case(b),
+        Language::Astro | Language::Svelte | Language::Vue => a == b,
+    }
 }
 
 /// Output code and its corresponding sourcemap JSON.
@@ -57,6 +419,8 @@ pub struct CodeAndSourcemap {
     pub code: String,
     /// The generated/re-written sourcemap JSON.
     pub map: String,
+    /// Statistics about what the rewrite did.
+    pub report: StripReport,
 }
 
 /// Strip inter-node whitespace and create a brand-new sourcemap.
@@ -69,9 +433,27 @@ pub fn strip_whitespace(
     language: Language,
     config: &StripConfig,
 ) -> Result<CodeAndSourcemap, StripError> {
-    let (code, edits) = rewrite(source, language, config)?;
+    let (code, edits, report) =
+        with_thread_parsers(|parsers| rewrite(parsers, source, language, config))?;
     let map = create_sourcemap(source, &code, source_filename, &edits)?;
-    Ok(CodeAndSourcemap { code, map })
+    Ok(CodeAndSourcemap { code, map, report })
+}
+
+/// Strip inter-node whitespace and rewrite an existing input sourcemap.
+///
+/// Use this when `source` was itself generated by an upstream tool (e.g. a preprocessor) that
+/// emitted `input_map_json`. The returned sourcemap maps the stripped output back to the
+/// original sources referenced by the input map, rather than to `source`.
+pub fn strip_whitespace_with_input_map(
+    source: &str,
+    input_map_json: &str,
+    language: Language,
+    config: &StripConfig,
+) -> Result<CodeAndSourcemap, StripError> {
+    let (code, edits, report) =
+        with_thread_parsers(|parsers| rewrite(parsers, source, language, config))?;
+    let map = rewrite_sourcemap(source, &code, input_map_json, &edits)?;
+    Ok(CodeAndSourcemap { code, map, report })
 }
 
 /// Strip inter-node whitespace without producing a sourcemap.
@@ -82,61 +464,209 @@ pub fn strip_whitespace_no_sourcemap(
     language: Language,
     config: &StripConfig,
 ) -> Result<String, StripError> {
-    let (code, _) = rewrite(source, language, config)?;
+    let (code, _, _) = with_thread_parsers(|parsers| rewrite(parsers, source, language, config))?;
     Ok(code)
 }
 
-/// Parse `source`, collect non-overlapping edits, apply them, and return `(output, edits)`.
+/// Strip inter-node whitespace without producing a sourcemap, and report what was done.
+pub fn strip_whitespace_with_report(
+    source: &str,
+    language: Language,
+    config: &StripConfig,
+) -> Result<(String, StripReport), StripError> {
+    let (code, _, report) =
+        with_thread_parsers(|parsers| rewrite(parsers, source, language, config))?;
+    Ok((code, report))
+}
+
+/// Compute the whitespace edits for `source` without applying them.
 ///
-/// This is the shared core used by all public entry points.
-fn rewrite(
+/// The returned edits are sorted by ascending `start`, non-overlapping, and validated with
+/// [`validate_edits`]. Apply them with [`apply_edits`](crate::edit::apply_edits), or merge them
+/// with your own edits (e.g. in a magic-string pipeline) before applying.
+pub fn plan_edits(
     source: &str,
     language: Language,
     config: &StripConfig,
-) -> Result<(String, Vec<Edit>), StripError> {
-    let src = source.as_bytes();
+) -> Result<Vec<Edit>, StripError> {
+    with_thread_parsers(|parsers| {
+        plan(
+            parsers,
+            source,
+            language,
+            config,
+            &mut StripReport::default(),
+        )
+    })
+}
 
-    let tree = parse(source, language)?;
+/// Shared implementation of [`plan_edits`] that also fills in `report`.
+pub(crate) fn plan(
+    parsers: &mut Parsers,
+    source: &str,
+    language: Language,
+    config: &StripConfig,
+    report: &mut StripReport,
+) -> Result<Vec<Edit>, StripError> {
+    parsers.set_timeout(config.parse_timeout);
+    let tree = parsers.parse(source, language)?;
     let root = tree.root_node();
+    check_syntax_errors(source, root, config)?;
 
     // Collect edits by walking the CST.
-    let edits = collect_edits(source, root, language, config);
+    let mut planned = collect_edits(source, root, language, config, report);
+    verify_protected_spans(root, &mut planned, |p| &p.edit, config)?;
+    report.record_edits(&planned);
 
     // Validate edits for overlaps.
-    validate_edits(src.len(), &edits)?;
+    let edits: Vec<Edit> = planned.into_iter().map(|p| p.edit).collect();
+    validate_edits(source.len(), &edits)?;
 
-    // Rebuild the output with byte-level origin mapping.
-    let mut out = Vec::<u8>::with_capacity(src.len());
-    let mut cursor = 0usize;
-    for edit in &edits {
-        // Copy unchanged bytes before the edit.
-        if cursor < edit.start {
-            out.extend_from_slice(&src[cursor..edit.start]);
-        }
+    Ok(edits)
+}
+
+/// Parse `source`, collect non-overlapping edits, apply them, and return
+/// `(output, edits, report)`.
+///
+/// This is the shared core used by all public entry points and [`Stripper`](crate::Stripper).
+pub(crate) fn rewrite(
+    parsers: &mut Parsers,
+    source: &str,
+    language: Language,
+    config: &StripConfig,
+) -> Result<(String, Vec<Edit>, StripReport), StripError> {
+    let mut report = StripReport::default();
+    let edits = plan(parsers, source, language, config, &mut report)?;
+    let out = apply_edits(source, &edits)?;
+    Ok((out, edits, report))
+}
+
+/// Fails with [`StripError::SyntaxError`] if `root` contains an error node and the policy is
+/// [`SyntaxErrorPolicy::Strict`].
+pub(crate) fn check_syntax_errors(
+    source: &str,
+    root: tree_sitter::Node<'_>,
+    config: &StripConfig,
+) -> Result<(), StripError> {
+    if config.syntax_errors == SyntaxErrorPolicy::Strict
+        && root.has_error()
+        && let Some(error) = first_syntax_error(root)
+    {
+        let (byte, pos) = (error.start_byte(), error.start_position());
+        // Tree-sitter columns count bytes; report UTF-16 code units like the sourcemaps do.
+        let line_prefix = source.get(byte - pos.column..byte).unwrap_or_default();
+        return Err(StripError::SyntaxError {
+            byte,
+            line: pos.row + 1,
+            column: line_prefix.encode_utf16().count() + 1,
+        });
+    }
+    Ok(())
+}
 
-        // Apply the edit replacement.
-        out.extend_from_slice(edit.replacement.as_bytes());
-        cursor = edit.end;
+/// Checks that no edit intersects a protected span under `root` (see [`ProtectedSpanPolicy`]).
+///
+/// With [`ProtectedSpanPolicy::Lenient`], offending edits are removed from `edits` instead.
+/// `edit` returns the [`Edit`] of an element of `edits`.
+pub(crate) fn verify_protected_spans<E>(
+    root: tree_sitter::Node<'_>,
+    edits: &mut Vec<E>,
+    edit: impl Fn(&E) -> &Edit,
+    config: &StripConfig,
+) -> Result<(), StripError> {
+    if edits.is_empty() {
+        return Ok(());
+    }
+    let spans = protected_spans(root);
+    // Spans are disjoint and in document order, so the only candidate for an edit is the first
+    // span ending after it starts.
+    let hit = |edit: &Edit| {
+        let i = spans.partition_point(|(span, _)| span.end <= edit.start);
+        spans
+            .get(i)
+            .filter(|(span, _)| span.start < edit.end)
+            .cloned()
+    };
+    match config.protected_spans {
+        ProtectedSpanPolicy::Strict => {
+            if let Some((edit, (span, kind))) = edits
+                .iter()
+                .map(&edit)
+                .find_map(|e| hit(e).map(|span| (e, span)))
+            {
+                return Err(StripError::ProtectedSpanEdited {
+                    edit_start: edit.start,
+                    edit_end: edit.end,
+                    span_start: span.start,
+                    span_end: span.end,
+                    kind,
+                });
+            }
+        }
+        ProtectedSpanPolicy::Lenient => edits.retain(|e| hit(edit(e)).is_none()),
     }
+    Ok(())
+}
+
+/// Returns the protected spans under `root` in document order, with a name for each: Astro
+/// `frontmatter`, the contents of `script`/`style` elements and any other `raw_text` node.
+fn protected_spans(root: tree_sitter::Node<'_>) -> Vec<(Range<usize>, &'static str)> {
+    let mut spans = Vec::new();
+    let mut cursor = root.walk();
+    'walk: loop {
+        let node = cursor.node();
+        let span = match node.kind() {
+            "frontmatter" => Some((node.byte_range(), "frontmatter")),
+            "script_element" => Some((element_contents(node), "script")),
+            "style_element" => Some((element_contents(node), "style")),
+            "raw_text" => Some((node.byte_range(), "raw text")),
+            _ => None,
+        };
+        if let Some(span) = span {
+            if !span.0.is_empty() {
+                spans.push(span);
+            }
+        } else if cursor.goto_first_child() {
+            continue;
+        }
 
-    // Copy any remaining unchanged bytes after the last edit.
-    if cursor < src.len() {
-        out.extend_from_slice(&src[cursor..]);
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
     }
+    spans
+}
 
-    let out = String::from_utf8(out).expect("output must be utf-8");
-    Ok((out, edits))
+/// Returns the byte range between the start and end tags of `element`. A missing tag leaves the
+/// range at the element's own start or end.
+fn element_contents(element: tree_sitter::Node<'_>) -> Range<usize> {
+    let mut cursor = element.walk();
+    let mut contents = element.byte_range();
+    for child in element.named_children(&mut cursor) {
+        match child.kind() {
+            "start_tag" => contents.start = child.end_byte(),
+            "end_tag" => contents.end = child.start_byte(),
+            _ => {}
+        }
+    }
+    contents
 }
 
 /// Walk the parsed AST and collect whitespace-gap rewrite edits.
 ///
-/// Returns a sorted list of edits to apply to `source`.
-fn collect_edits(
+/// Returns a sorted list of edits to apply to `source`, tagged for the report.
+pub(crate) fn collect_edits(
     source: &str,
     node: tree_sitter::Node<'_>,
     language: Language,
     config: &StripConfig,
-) -> Vec<Edit> {
+    report: &mut StripReport,
+) -> Vec<PlannedEdit> {
     // Iterative traversal that uses a TreeCursor and never indexes children by integer.
     //
     // This avoids:
@@ -145,128 +675,125 @@ fn collect_edits(
     // - wasm panics where `child_count`/`child(i)` can disagree for some nodes
     //
     // We only apply gap edits to "container" nodes. For Astro: `document` and `element`.
-    // For Svelte: `fragment` and `element`. We intentionally do NOT treat interpolation/expression
-    // nodes as containers because whitespace within `{ ... }` is part of JavaScript and can be
-    // semantically meaningful.
-
-    fn process_container_gaps(
-        source: &str,
-        node: tree_sitter::Node<'_>,
-        language: Language,
-        config: &StripConfig,
-        edits: &mut Vec<Edit>,
-    ) {
-        let mut cursor = node.walk();
-        let mut prev_named: Option<tree_sitter::Node<'_>> = None;
-
-        for next in node.named_children(&mut cursor) {
-            let Some(prev) = prev_named else {
-                prev_named = Some(next);
-                continue;
-            };
-            prev_named = Some(next);
-
-            if prev.end_byte() >= next.start_byte() {
-                continue;
-            }
-
-            let gap_start = prev.end_byte();
-            let gap_end = next.start_byte();
+    // For Svelte: `fragment`, `element` and control flow/snippet blocks. For Vue:
+    // `template_element` and `element`. For HTML: `document` and `element`. We intentionally do
+    // NOT treat interpolation/expression/`{@...}` tag nodes as containers because whitespace
+    // within `{ ... }` is part of JavaScript and can be semantically meaningful.
+    //
+    // Whitespace-sensitive elements (e.g. `<pre>`) are not descended into at all, so none of
+    // their descendants are rewritten.
+    //
+    // Comment directives are picked up along the way and turned into protected byte ranges;
+    // edits intersecting any of them are dropped once the walk is complete.
+    //
+    // `data-strip-whitespace="off"` / `"on"` markers switch stripping off or on for the
+    // containers in an element's subtree, including the element itself. Tag rules are applied
+    // the same way, except that `Preserve`/`StripOutside` are not descended into at all.
+
+    if language == Language::Svelte && svelte_preserves_whitespace(source, node) {
+        report.suppressions.svelte_preserve_whitespace = true;
+        return Vec::new();
+    }
+
+    let mut edits: Vec<PlannedEdit> = Vec::new();
+    let mut protected: Vec<(usize, usize)> = Vec::new();
+    let mut disabled_from: Option<usize> = None;
+    // `(depth, mode)` set by markers and tag rules on the current node's ancestors; entries at or
+    // below the current depth belong to subtrees the walk has left.
+    let has_markers = source.contains(MARKER_ATTRIBUTE);
+    let mut modes: Vec<(u32, SubtreeMode)> = Vec::new();
+    let mut cursor = node.walk();
+    'walk: loop {
+        let current = cursor.node();
+        let kind = current.kind();
+        let depth = cursor.depth();
+        while modes.last().is_some_and(|&(d, _)| d >= depth) {
+            modes.pop();
+        }
 
-            let gap = &source[gap_start..gap_end];
-            if gap.is_empty() || !gap.chars().all(|c| c.is_whitespace()) {
-                continue;
+        let action = element_action(source, current, language, config);
+        let mut mode = match action {
+            Some(TagAction::Strip | TagAction::StripChildren) => Some(SubtreeMode::Strip),
+            Some(TagAction::CollapseToSpace) => Some(SubtreeMode::CollapseToSpace),
+            Some(TagAction::Preserve | TagAction::StripOutside) => None,
+            None => svelte_special_element_mode(source, current, language),
+        };
+        if has_markers
+            && kind == "element"
+            && let Some(attr) = element_attribute(source, current, MARKER_ATTRIBUTE)
+        {
+            match attribute_value(source, attr) {
+                Some(value) if value.eq_ignore_ascii_case("off") => mode = Some(SubtreeMode::Off),
+                Some(value) if value.eq_ignore_ascii_case("on") => mode = Some(SubtreeMode::Strip),
+                _ => {}
             }
-
-            // Preserve intentional section breaks.
-            if config.preserve_blank_lines && contains_blank_line(gap) {
-                continue;
+            if config.remove_marker_attributes {
+                edits.push(PlannedEdit::new(
+                    remove_attribute(source, attr),
+                    EditKind::RemovedMarkerAttribute,
+                ));
             }
-
-            // Case 1: rotate a trailing delimiter from the previous node (">", "/>", "-->", "}")
-            // to sit immediately before the next node.
-            if let Some(delim) = TrailingDelim::from_node(prev, language) {
-                let delim_len = delim.len();
-                if prev.end_byte() >= delim_len {
-                    let delim_pos = prev.end_byte() - delim_len;
-                    if source.as_bytes().get(delim_pos..prev.end_byte()) == Some(delim.bytes()) {
-                        let (replacement, input_offset_for_output) =
-                            rotate_delim_over_gap(delim, gap);
-
-                        let output_byte_to_input_byte = input_offset_for_output
-                            .iter()
-                            .map(|&in_off| Some(delim_pos + in_off))
-                            .collect();
-
-                        edits.push(Edit {
-                            start: delim_pos,
-                            end: gap_end,
-                            replacement,
-                            output_byte_to_input_byte,
-                            moved_delim_len: delim_len,
-                        });
-                        continue;
+        }
+        if let Some(mode) = mode {
+            modes.push((depth, mode));
+        }
+        let mode = modes.last().map_or(SubtreeMode::Strip, |&(_, mode)| mode);
+
+        if kind == "comment" {
+            match Directive::from_comment(source, current) {
+                Some(Directive::IgnoreFile) => {
+                    // Nothing counted so far is going to happen.
+                    *report = StripReport::default();
+                    report.suppressions.ignore_file = true;
+                    return Vec::new();
+                }
+                Some(Directive::Ignore) => {
+                    let mut target = current.next_named_sibling();
+                    while let Some(t) = target.filter(|t| t.kind() == "comment") {
+                        target = t.next_named_sibling();
+                    }
+                    if let Some(target) = target {
+                        protected.push((target.start_byte(), target.end_byte()));
+                    }
+                }
+                Some(Directive::Disable) => {
+                    disabled_from.get_or_insert(current.end_byte());
+                }
+                Some(Directive::Enable) => {
+                    if let Some(from) = disabled_from.take() {
+                        protected.push((from, current.start_byte()));
                     }
                 }
+                None => {}
             }
+        }
 
-            // Case 2: if the previous node is text and the next node begins with an opener
-            // ("<!--", "<tag", "</tag", "{"), rotate that opener left across the gap so it
-            // becomes adjacent to the text.
-            if prev.kind() == "text"
-                && let Some(prefix_end) = opener_prefix_end(source, next, language)
-                && prefix_end > gap_end
-            {
-                let prefix = &source.as_bytes()[gap_end..prefix_end];
-                let (replacement, input_offset_for_output) = rotate_prefix_over_gap(prefix, gap);
-
-                let output_byte_to_input_byte = input_offset_for_output
-                    .iter()
-                    .map(|&in_off| Some(gap_start + in_off))
-                    .collect();
-
-                edits.push(Edit {
-                    start: gap_start,
-                    end: prefix_end,
-                    replacement,
-                    output_byte_to_input_byte,
-                    moved_delim_len: 0,
-                });
+        let skip_subtree = match action {
+            Some(TagAction::Preserve | TagAction::StripOutside) => true,
+            Some(_) => false,
+            None => {
+                let raw = is_raw_element(source, current, language);
+                if raw {
+                    report.suppressions.astro_raw_elements += 1;
+                }
+                raw || is_whitespace_sensitive_element(source, current, language, config)
             }
+        };
+        let skip_gaps =
+            config.syntax_errors == SyntaxErrorPolicy::SkipErrorSubtrees && current.has_error();
+        if mode != SubtreeMode::Off
+            && !skip_gaps
+            && config.attribute_whitespace != AttributeWhitespace::Preserve
+            && matches!(kind, "start_tag" | "self_closing_tag")
+        {
+            collapse_attribute_whitespace(source, current, config, &mut edits);
         }
-    }
-
-    // Determine which node kinds are containers based on the language
-    fn is_container(kind: &str, language: Language) -> bool {
-        matches!(
-            (language, kind),
-            (Language::Astro, "document" | "element")
-                | (
-                    Language::Svelte,
-                    "document"
-                        | "fragment"
-                        | "element"
-                        | "if_statement"
-                        | "each_statement"
-                        | "await_statement"
-                        | "key_statement"
-                        | "else_block"
-                        | "else_if_block"
-                )
-        )
-    }
-
-    let mut edits: Vec<Edit> = Vec::new();
-    let mut cursor = node.walk();
-    'walk: loop {
-        let current = cursor.node();
-        let kind = current.kind();
-        if is_container(kind, language) {
-            process_container_gaps(source, current, language, config, &mut edits);
+        if mode != SubtreeMode::Off && !skip_subtree && !skip_gaps && is_container(kind, language) {
+            process_container_gaps(source, current, language, config, mode, &mut edits, report);
         }
 
         // Descend if possible.
-        if cursor.goto_first_child() {
+        if !skip_subtree && cursor.goto_first_child() {
             continue;
         }
 
@@ -281,7 +808,19 @@ fn collect_edits(
         }
     }
 
-    edits.sort_by(|a, b| {
+    // An unterminated disable directive extends to the end of the file.
+    if let Some(from) = disabled_from {
+        protected.push((from, source.len()));
+    }
+    if !protected.is_empty() {
+        edits.retain(|PlannedEdit { edit: e, .. }| {
+            !protected
+                .iter()
+                .any(|&(start, end)| e.start < end && start < e.end)
+        });
+    }
+
+    edits.sort_by(|PlannedEdit { edit: a, .. }, PlannedEdit { edit: b, .. }| {
         a.start
             .cmp(&b.start)
             .then(a.end.cmp(&b.end))
@@ -291,681 +830,2422 @@ fn collect_edits(
     edits
 }
 
-/// Trailing delimiters that can be rotated across a whitespace gap.
+/// Computes the gap edits between the named children of the container `node`.
 ///
-/// The delimiter is detected by node kind and then verified against source bytes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum TrailingDelim {
-    Gt,
-    SlashGt,
-    CommentEnd,
-    RBrace,
-}
-
-impl TrailingDelim {
-    /// Returns the literal bytes of the delimiter.
-    fn bytes(self) -> &'static [u8] {
-        match self {
-            TrailingDelim::Gt => b">",
-            TrailingDelim::SlashGt => b"/>",
-            TrailingDelim::CommentEnd => b"-->",
-            TrailingDelim::RBrace => b"}",
+/// With [`SubtreeMode::CollapseToSpace`], every gap is collapsed to a single space instead of
+/// rotated (see [`TagAction::CollapseToSpace`]). With [`SubtreeMode::Unrendered`], blank lines
+/// and spaces between inline neighbours are not kept.
+pub(crate) fn process_container_gaps(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+    mode: SubtreeMode,
+    edits: &mut Vec<PlannedEdit>,
+    report: &mut StripReport,
+) {
+    let collapse = mode == SubtreeMode::CollapseToSpace;
+    let unrendered = mode == SubtreeMode::Unrendered;
+    let preserve_blank_lines = config.preserve_blank_lines && !unrendered;
+    let mut cursor = node.walk();
+    let mut prev_named: Option<tree_sitter::Node<'_>> = None;
+    // Span of the comments removed since `prev_named`, including the whitespace between them,
+    // and the spans of the comments in it.
+    let mut comments: Option<Range<usize>> = None;
+    let mut comment_spans: Vec<Range<usize>> = Vec::new();
+
+    for next in node.named_children(&mut cursor) {
+        if config.remove_comments && is_removable_comment(source, next) {
+            let run = match comments.take() {
+                Some(run) if is_blank(&source[run.end..next.start_byte()]) => {
+                    run.start..next.end_byte()
+                }
+                Some(run) => {
+                    edits.push(comment_deletion(run, comment_spans.len()));
+                    comment_spans.clear();
+                    next.start_byte()..next.end_byte()
+                }
+                None => next.start_byte()..next.end_byte(),
+            };
+            comments = Some(run);
+            comment_spans.push(next.byte_range());
+            continue;
         }
-    }
 
-    /// Returns the delimiter length in bytes.
-    fn len(self) -> usize {
-        self.bytes().len()
-    }
+        if next.kind() == "text" && config.text_whitespace != TextWhitespace::Preserve {
+            collapse_text_whitespace(source, next, config.text_whitespace, edits);
+        }
 
-    /// Attempts to infer the delimiter type that ends `node`.
-    ///
-    /// For `element`, we inspect its last named child to find the real trailing token.
-    fn from_node(node: tree_sitter::Node<'_>, language: Language) -> Option<Self> {
-        match (language, node.kind()) {
-            (_, "start_tag" | "end_tag") => Some(TrailingDelim::Gt),
-            (_, "self_closing_tag") => Some(TrailingDelim::SlashGt),
-            (_, "comment") => Some(TrailingDelim::CommentEnd),
-            (Language::Astro, "html_interpolation") => Some(TrailingDelim::RBrace),
-            (Language::Svelte, "expression") => Some(TrailingDelim::RBrace),
-            // Svelte control flow blocks end with }
-            (
-                Language::Svelte,
-                "if_start" | "else_if_start" | "if_end" | "each_start" | "each_end" | "await_start"
-                | "await_end" | "key_start" | "key_end" | "else_start",
-            ) => Some(TrailingDelim::RBrace),
-            (_, "element") => {
-                // Find the actual trailing delimiter from the element's last child.
-                let mut cursor = node.walk();
-                let last_child = node.named_children(&mut cursor).last()?;
-                TrailingDelim::from_node(last_child, language)
+        let removed = comments.take();
+        let removed_spans = std::mem::take(&mut comment_spans);
+        let Some(prev) = prev_named.replace(next) else {
+            // Leading comments take the whitespace after them along.
+            if let Some(run) = removed {
+                let run = widen_end(source, run, next.start_byte());
+                edits.push(comment_deletion(run, removed_spans.len()));
             }
-            // Svelte statements end with their end tags
-            (
-                Language::Svelte,
-                "if_statement" | "each_statement" | "await_statement" | "key_statement",
-            ) => {
-                // Find the actual trailing delimiter from the statement's last child.
-                let mut cursor = node.walk();
-                let last_child = node.named_children(&mut cursor).last()?;
-                TrailingDelim::from_node(last_child, language)
+            continue;
+        };
+        // With comments removed, the gap is the whitespace after the last one; everything
+        // between `prev` and `next` must be blank apart from the comments.
+        let merged = removed
+            .as_ref()
+            .is_some_and(|run| is_blank(&source[prev.end_byte()..run.start]));
+        let edits_before = edits.len();
+
+        'gap: {
+            // Tag rules can keep the gaps around an element.
+            if keeps_surrounding_gaps(source, prev, language, config)
+                || keeps_surrounding_gaps(source, next, language, config)
+            {
+                break 'gap;
             }
-            // else_block ends with its last child's delimiter
-            (Language::Svelte, "else_block" | "else_if_block") => {
-                let mut cursor = node.walk();
-                let last_child = node.named_children(&mut cursor).last()?;
-                TrailingDelim::from_node(last_child, language)
+            if removed.is_some() && !merged {
+                break 'gap;
             }
-            _ => None,
-        }
-    }
-}
 
-/// Returns the byte offset of the end of the "opener prefix" for `next`.
-///
-/// This is used for the "rotate opener prefix left" transformation.
-///
-/// Examples (prefixes moved):
-///
-/// - `comment`: `<!--`
-/// - `html_interpolation` (Astro) / `expression` (Svelte): `{`
-/// - `start_tag`/`end_tag`/`self_closing_tag`: `<tagname` (up through the `tag_name` node)
-fn opener_prefix_end(
-    source: &str,
-    next: tree_sitter::Node<'_>,
-    language: Language,
-) -> Option<usize> {
-    let start = next.start_byte();
-    let bytes = source.as_bytes();
+            if config.trim_block_text
+                && !collapse
+                && let Some((start, end)) =
+                    block_text_edge(source, node, prev, next, removed.clone(), language, config)
+                && !(preserve_blank_lines && contains_blank_line(&source[start..end]))
+            {
+                report.gaps_examined += 1;
+                edits.push(PlannedEdit::new(
+                    deletion(start..end),
+                    EditKind::TrimmedBlockText,
+                ));
+                break 'gap;
+            }
 
-    match (language, next.kind()) {
-        // Astro uses "html_interpolation", Svelte uses "expression"
-        (Language::Astro, "html_interpolation") | (Language::Svelte, "expression") => {
-            if bytes.get(start) == Some(&b'{') {
-                Some(start + 1)
-            } else {
-                None
+            if prev.end_byte() >= next.start_byte() {
+                break 'gap;
             }
-        }
-        // Svelte control flow blocks: `{#if}`, `{#each}`, `{:else}`, `{/if}`, etc.
-        (
-            Language::Svelte,
-            "if_start" | "else_if_start" | "else_start" | "if_end" | "each_start" | "each_end"
-            | "await_start" | "await_end" | "key_start" | "key_end",
-        ) => {
-            if bytes.get(start) == Some(&b'{') {
-                Some(start + 1)
-            } else {
-                None
+
+            let gap_start = prev.end_byte();
+            let gap_end = next.start_byte();
+
+            let gap = match &removed {
+                Some(run) => &source[run.end..gap_end],
+                None => &source[gap_start..gap_end],
+            };
+            if (gap.is_empty() && removed.is_none()) || !gap.chars().all(|c| c.is_whitespace()) {
+                break 'gap;
             }
-        }
-        (_, "comment") => {
-            if bytes.get(start..start + 4) == Some(b"<!--") {
-                Some(start + 4)
-            } else {
-                None
+            report.gaps_examined += 1;
+
+            // Preserve intentional section breaks.
+            let before_comments = removed
+                .as_ref()
+                .map_or("", |run| &source[gap_start..run.start]);
+            if preserve_blank_lines
+                && (contains_blank_line(gap) || contains_blank_line(before_comments))
+            {
+                report.skipped_blank_lines += 1;
+                break 'gap;
             }
+
+            // Between two inline neighbours the gap renders as a space, so keep exactly one.
+            if collapse
+                || config.inline_safe
+                    && !unrendered
+                    && is_inline_neighbour(source, node, prev, language, config)
+                    && is_inline_neighbour(source, node, next, language, config)
+            {
+                if (gap != " " || removed.is_some()) && gap.bytes().all(|b| b.is_ascii_whitespace())
+                {
+                    let origin = if gap.is_empty() {
+                        gap_start
+                    } else {
+                        gap_end - gap.len()
+                    };
+                    let edit = Edit {
+                        start: gap_start,
+                        end: gap_end,
+                        replacement: " ".to_string(),
+                        output_byte_to_input_byte: vec![Some(origin)],
+                        moved_delim_len: 0,
+                    };
+                    edits.push(PlannedEdit::new(edit, EditKind::InlineGap));
+                }
+                break 'gap;
+            }
+
+            let planned = match &config.gap_strategy {
+                GapStrategy::Rotate => rotate_gap(source, prev, next, gap, language),
+                GapStrategy::Delete => DeletingGapRewriter
+                    .rewrite_gap(source, prev, next, gap, language)
+                    .map(|edit| PlannedEdit::new(edit, EditKind::DeletedGap)),
+                GapStrategy::Custom(rewriter) => rewriter
+                    .rewrite_gap(source, prev, next, gap, language)
+                    .map(|edit| PlannedEdit::new(edit, EditKind::CustomGap)),
+            };
+            edits.extend(planned);
         }
-        (_, "element") => {
-            // Move "<" + tag_name for the element's start tag.
-            let mut cursor = next.walk();
-            let tag = next
-                .named_children(&mut cursor)
-                .find(|c| c.kind() == "start_tag" || c.kind() == "self_closing_tag")?;
-            opener_prefix_end(source, tag, language)
-        }
-        // Svelte statements: move the opening "{" from the first child
-        (
-            Language::Svelte,
-            "if_statement" | "each_statement" | "await_statement" | "key_statement",
-        ) => {
-            let mut cursor = next.walk();
-            let first_child = next.named_children(&mut cursor).next()?;
-            opener_prefix_end(source, first_child, language)
-        }
-        (_, "start_tag" | "end_tag" | "self_closing_tag") => {
-            if bytes.get(start) != Some(&b'<') {
-                return None;
+
+        let Some(run) = removed else {
+            continue;
+        };
+        let swallowed = edits[edits_before..]
+            .iter_mut()
+            .find(|p| p.edit.start <= run.start && run.end <= p.edit.end);
+        match swallowed {
+            // The gap's edit deletes the comments, except those a custom rewriter maps output
+            // bytes back into.
+            Some(planned) => {
+                planned.comments = match planned.kind {
+                    EditKind::CustomGap => removed_spans
+                        .iter()
+                        .filter(|span| !maps_into(&planned.edit, span))
+                        .count(),
+                    _ => removed_spans.len(),
+                };
             }
-            let mut cursor = next.walk();
-            let tag_name = next
-                .named_children(&mut cursor)
-                .find(|c| c.kind() == "tag_name")?;
-            if tag_name.end_byte() > start {
-                Some(tag_name.end_byte())
-            } else {
-                None
+            // Other comments are deleted with the whitespace on one side, keeping a blank line
+            // if there is one.
+            None => {
+                let before = source.get(prev.end_byte()..run.start).unwrap_or_default();
+                let after = &source[run.end..next.start_byte()];
+                let run = if contains_blank_line(after) && !contains_blank_line(before) {
+                    widen_start(source, prev.end_byte(), run)
+                } else {
+                    widen_end(source, run, next.start_byte())
+                };
+                edits.push(comment_deletion(run, removed_spans.len()));
             }
         }
-        _ => None,
+    }
+
+    // Trailing comments (only possible in a document) go with the whitespace before them.
+    if let Some(run) = comments {
+        let run = match prev_named {
+            Some(prev) => widen_start(source, prev.end_byte(), run),
+            None => run,
+        };
+        edits.push(comment_deletion(run, comment_spans.len()));
     }
 }
 
-/// Returns true if a whitespace-only gap contains a "blank line".
+/// Returns true if `comment` is a `comment` node that [`StripConfig::remove_comments`] deletes.
 ///
-/// A blank line is defined as two consecutive line breaks (`\n\n` or `\r\n\r\n`).
-fn contains_blank_line(ws: &str) -> bool {
-    // Handles both LF and CRLF. We treat an empty line as any occurrence of
-    // two consecutive line breaks in the whitespace gap.
-    let b = ws.as_bytes();
-    if b.windows(2).any(|w| w == b"\n\n") {
-        return true;
-    }
-    if b.windows(4).any(|w| w == b"\r\n\r\n") {
-        return true;
+/// Kept: comment directives, Svelte's `svelte-ignore` comments, conditional comments
+/// (`<!--[if IE]>`, `<![endif]-->`) and license comments (`<!--! ... -->`).
+fn is_removable_comment(source: &str, comment: tree_sitter::Node<'_>) -> bool {
+    if comment.kind() != "comment" || Directive::from_comment(source, comment).is_some() {
+        return false;
+    }
+    let Some(body) = source
+        .get(comment.start_byte()..comment.end_byte())
+        .and_then(|text| text.strip_prefix("<!--"))
+        .and_then(|text| text.strip_suffix("-->"))
+    else {
+        return false;
+    };
+    !(body.starts_with('!')
+        || body.starts_with('[')
+        || body.ends_with(']')
+        || body.trim_start().starts_with("svelte-ignore"))
+}
+
+/// Returns true if `s` is empty or only ASCII whitespace.
+fn is_blank(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_whitespace())
+}
+
+/// Widens `run` back to `start` if only whitespace lies between them.
+fn widen_start(source: &str, start: usize, run: Range<usize>) -> Range<usize> {
+    if start <= run.start && is_blank(&source[start..run.start]) {
+        start..run.end
+    } else {
+        run
     }
-    false
 }
 
-/// Rotates a trailing delimiter from `prev` across the whitespace `gap`.
-///
-/// Input segment: `delim + gap`
-/// Output segment: `(optional stolen indent) + gap' + delim`
-///
-/// Returns `(replacement, input_offset_for_output)` where each output byte is mapped back to
-/// its originating input offset within the input segment.
-fn rotate_delim_over_gap(delim: TrailingDelim, gap: &str) -> (String, Vec<usize>) {
-    // Original segment is: delim + gap
-    // We want: (optional stolen indent) + gap' + delim
-    // Where stolen indent is one space/tab "stolen" from the end of indentation on the line
-    // where the next node begins, to preserve column numbers when possible.
+/// Widens `run` forward to `end` if only whitespace lies between them.
+fn widen_end(source: &str, run: Range<usize>, end: usize) -> Range<usize> {
+    if run.end <= end && is_blank(&source[run.end..end]) {
+        run.start..end
+    } else {
+        run
+    }
+}
 
-    let delim_bytes = delim.bytes();
-    let delim_len = delim_bytes.len();
+/// Returns an edit deleting `range`.
+fn deletion(range: Range<usize>) -> Edit {
+    Edit {
+        start: range.start,
+        end: range.end,
+        replacement: String::new(),
+        output_byte_to_input_byte: Vec::new(),
+        moved_delim_len: 0,
+    }
+}
 
-    let gap_bytes = gap.as_bytes();
-    let gap_len = gap_bytes.len();
+/// Returns true if any output byte of `edit` maps back into `span`.
+fn maps_into(edit: &Edit, span: &Range<usize>) -> bool {
+    edit.output_byte_to_input_byte
+        .iter()
+        .flatten()
+        .any(|byte| span.contains(byte))
+}
 
-    // Find the start of the final line within `gap`.
-    let mut last_line_start = 0usize;
-    for (i, &b) in gap_bytes.iter().enumerate() {
-        if b == b'\n' {
-            last_line_start = i + 1;
-        }
+/// Returns an edit deleting `range`, which holds `count` removed comments.
+fn comment_deletion(range: Range<usize>, count: usize) -> PlannedEdit {
+    PlannedEdit {
+        edit: deletion(range),
+        kind: EditKind::RemovedComments,
+        comments: count,
     }
+}
 
-    // Identify "stealable" whitespace bytes on that final line.
-    // For "/>" we steal up to 2 bytes to preserve the next node's column.
-    // For other delimiters, keep the historical behavior (steal at most 1 byte).
-    let max_steal = match delim {
-        TrailingDelim::SlashGt => 2,
-        _ => 1,
-    };
+/// Rewrites the whitespace `gap` between `prev` and `next` by rotating a delimiter across it;
+/// see [`RotatingGapRewriter`](crate::gap::RotatingGapRewriter).
+pub(crate) fn rotate_gap(
+    source: &str,
+    prev: tree_sitter::Node<'_>,
+    next: tree_sitter::Node<'_>,
+    gap: &str,
+    language: Language,
+) -> Option<PlannedEdit> {
+    let gap_start = prev.end_byte();
+    let gap_end = next.start_byte();
+    // The gap is the whitespace right before `next`; removed comments may precede it.
+    let gap_pos = gap_end - gap.len();
+
+    // Case 1: rotate a trailing delimiter from the previous node (">", "/>", "-->", "}")
+    // to sit immediately before the next node.
+    if let Some(delim) = TrailingDelim::from_node(prev, language) {
+        let delim_len = delim.len();
+        if prev.end_byte() >= delim_len {
+            let delim_pos = prev.end_byte() - delim_len;
+            if source.as_bytes().get(delim_pos..prev.end_byte()) == Some(delim.bytes()) {
+                let (replacement, input_offset_for_output) = rotate_delim_over_gap(delim, gap);
 
-    let mut steal_indices: Vec<usize> = Vec::new();
-    if max_steal > 0 && last_line_start < gap_len {
-        let mut i = gap_len;
-        while i > last_line_start && steal_indices.len() < max_steal {
-            let b = gap_bytes[i - 1];
-            if matches!(b, b' ' | b'\t') {
-                steal_indices.push(i - 1);
-                i -= 1;
-            } else {
-                break;
+                let output_byte_to_input_byte = input_offset_for_output
+                    .iter()
+                    .map(|&in_off| {
+                        Some(if in_off < delim_len {
+                            delim_pos + in_off
+                        } else {
+                            gap_pos + in_off - delim_len
+                        })
+                    })
+                    .collect();
+
+                let edit = Edit {
+                    start: delim_pos,
+                    end: gap_end,
+                    replacement,
+                    output_byte_to_input_byte,
+                    moved_delim_len: delim_len,
+                };
+                return Some(PlannedEdit::new(edit, EditKind::DelimRotation(delim)));
             }
         }
-        steal_indices.sort_unstable();
     }
 
-    let mut replacement = Vec::<u8>::with_capacity(delim_len + gap_len);
-    let mut input_offset_for_output: Vec<usize> = Vec::with_capacity(delim_len + gap_len);
+    // Case 2: if the previous node is text and the next node begins with an opener
+    // ("<!--", "<tag", "</tag", "{"), rotate that opener left across the gap so it
+    // becomes adjacent to the text.
+    if prev.kind() == "text"
+        && let Some(prefix_end) = opener_prefix_end(source, next, language)
+        && prefix_end > gap_end
+    {
+        let prefix = &source.as_bytes()[gap_end..prefix_end];
+        let (replacement, input_offset_for_output) = rotate_prefix_over_gap(prefix, gap);
+
+        let output_byte_to_input_byte = input_offset_for_output
+            .iter()
+            .map(|&in_off| {
+                Some(if in_off < gap.len() {
+                    gap_pos + in_off
+                } else {
+                    gap_end + in_off - gap.len()
+                })
+            })
+            .collect();
+
+        let edit = Edit {
+            start: gap_start,
+            end: prefix_end,
+            replacement,
+            output_byte_to_input_byte,
+            moved_delim_len: 0,
+        };
+        let prefix_len = prefix.len();
+        return Some(PlannedEdit::new(
+            edit,
+            EditKind::PrefixRotation { prefix_len },
+        ));
+    }
 
-    // Source segment indices:
-    // - delim is at input offsets 0..delim_len
-    // - gap bytes are at input offsets delim_len..(delim_len + gap_len)
+    None
+}
 
-    if !steal_indices.is_empty() {
-        // Output stolen bytes first.
-        for &steal_i in &steal_indices {
-            replacement.push(gap_bytes[steal_i]);
-            input_offset_for_output.push(delim_len + steal_i);
+/// Returns the whitespace range to delete between the siblings `prev` and `next` of `container`
+/// if one of them is text and the other is a block-level boundary.
+///
+/// The range covers the gap plus any whitespace at the facing edge of the text node, and the
+/// `removed` comments in the gap, if any.
+fn block_text_edge(
+    source: &str,
+    container: tree_sitter::Node<'_>,
+    prev: tree_sitter::Node<'_>,
+    next: tree_sitter::Node<'_>,
+    removed: Option<Range<usize>>,
+    language: Language,
+    config: &StripConfig,
+) -> Option<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let (mut start, mut end) = (prev.end_byte(), next.start_byte());
+    if prev.kind() == "text" && is_block_boundary(source, container, next, language, config) {
+        while start > prev.start_byte() && bytes[start - 1].is_ascii_whitespace() {
+            start -= 1;
         }
-
-        // Then output the gap minus the stolen bytes.
-        for (i, &b) in gap_bytes.iter().enumerate() {
-            if steal_indices.binary_search(&i).is_ok() {
-                continue;
-            }
-            replacement.push(b);
-            input_offset_for_output.push(delim_len + i);
+    } else if next.kind() == "text" && is_block_boundary(source, container, prev, language, config)
+    {
+        while end < next.end_byte() && bytes[end].is_ascii_whitespace() {
+            end += 1;
         }
-
-        // And finally the delimiter bytes.
-        for (i, &b) in delim_bytes.iter().enumerate() {
-            replacement.push(b);
-            input_offset_for_output.push(i);
+        // Whitespace-only text is left to the edge after it, so the two edits don't overlap.
+        if end == next.end_byte() {
+            end = next.start_byte();
         }
+    } else {
+        return None;
+    }
 
-        return (
-            String::from_utf8(replacement).expect("replacement must be utf-8"),
-            input_offset_for_output,
-        );
+    // Removed comments are deleted along with the whitespace.
+    let (before, after) = match removed {
+        Some(run) => (bytes.get(start..run.start)?, bytes.get(run.end..end)?),
+        None => (bytes.get(start..end)?, &[][..]),
+    };
+    let ws = || before.iter().chain(after);
+    (ws().next().is_some() && ws().all(u8::is_ascii_whitespace)).then_some((start, end))
+}
+
+/// Returns true if the child `node` of `container` marks a block-level boundary: the start or
+/// end tag of a block-level `container`, or a block-level sibling element.
+fn is_block_boundary(
+    source: &str,
+    container: tree_sitter::Node<'_>,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> bool {
+    let element = match node.kind() {
+        "start_tag" | "end_tag" => container,
+        "element" => node,
+        _ => return false,
+    };
+    element.kind() == "element"
+        && element_tag_name(source, element)
+            .is_some_and(|tag_name| config.is_block_level(tag_name, language))
+}
+
+/// Returns true if the child `node` of `container` renders inline, so a space next to it can
+/// show up in the output: it is neither a block-level boundary nor a node that renders nothing
+/// in place (see [`is_non_rendering`]).
+fn is_inline_neighbour(
+    source: &str,
+    container: tree_sitter::Node<'_>,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> bool {
+    !is_block_boundary(source, container, node, language, config)
+        && !is_non_rendering(source, node, language)
+}
+
+/// Tag names of HTML elements that render nothing where they appear.
+const NON_RENDERING_ELEMENTS: &[&str] = &["base", "link", "meta", "script", "style", "title"];
+
+/// Returns true if `node` renders nothing where it appears: a doctype, Astro frontmatter, a
+/// `<script>` or `<style>` element, or head metadata like `<meta>`, `<link>` and `<title>`.
+fn is_non_rendering(source: &str, node: tree_sitter::Node<'_>, language: Language) -> bool {
+    match node.kind() {
+        "doctype" | "frontmatter" | "script_element" | "style_element" => true,
+        "element" => element_tag_name(source, node).is_some_and(|tag_name| {
+            NON_RENDERING_ELEMENTS
+                .iter()
+                .any(|name| tag_names_eq(name, tag_name, language))
+        }),
+        _ => false,
     }
+}
 
-    // No indentation to steal; just rotate: gap + delim.
-    for (i, &b) in gap_bytes.iter().enumerate() {
-        replacement.push(b);
-        input_offset_for_output.push(delim_len + i);
+/// Collapses the whitespace runs between the words of the `text` node according to `mode`.
+fn collapse_text_whitespace(
+    source: &str,
+    text: tree_sitter::Node<'_>,
+    mode: TextWhitespace,
+    edits: &mut Vec<PlannedEdit>,
+) {
+    let bytes = source.as_bytes();
+    let (start, end) = (text.start_byte(), text.end_byte());
+    let mut i = start;
+    while i < end {
+        if !bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let run_start = i;
+        while i < end && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        // Leading and trailing whitespace borders other nodes; that is a gap, not text.
+        if run_start == start || i == end {
+            continue;
+        }
+
+        let keep_newline = mode == TextWhitespace::CollapseToNewline;
+        let edit = collapse_run(source, run_start, i, keep_newline);
+        edits.extend(edit.map(|edit| PlannedEdit::new(edit, EditKind::CollapsedTextRun)));
     }
-    for (i, &b) in delim_bytes.iter().enumerate() {
-        replacement.push(b);
-        input_offset_for_output.push(i);
+}
+
+/// Collapses the whitespace between the tag name and attributes of the tag `tag` according to
+/// [`StripConfig::attribute_whitespace`].
+fn collapse_attribute_whitespace(
+    source: &str,
+    tag: tree_sitter::Node<'_>,
+    config: &StripConfig,
+    edits: &mut Vec<PlannedEdit>,
+) {
+    let keep_lines = config.attribute_whitespace == AttributeWhitespace::KeepLineBreaks;
+    let mut cursor = tag.walk();
+    let mut prev_named: Option<tree_sitter::Node<'_>> = None;
+    for next in tag.named_children(&mut cursor) {
+        let Some(prev) = prev_named.replace(next) else {
+            continue;
+        };
+        let (start, end) = (prev.end_byte(), next.start_byte());
+        if start >= end
+            || !source.as_bytes()[start..end]
+                .iter()
+                .all(u8::is_ascii_whitespace)
+        {
+            continue;
+        }
+        // A removed marker takes the whitespace before it along.
+        if config.remove_marker_attributes && attribute_name(source, next) == Some(MARKER_ATTRIBUTE)
+        {
+            continue;
+        }
+        let run = &source[start..end];
+        let edit = match run.rfind('\n') {
+            Some(nl) if keep_lines => {
+                let keep_from = if run[..nl].ends_with('\r') {
+                    nl - 1
+                } else {
+                    nl
+                };
+                (keep_from > 0).then(|| Edit {
+                    start,
+                    end,
+                    replacement: run[keep_from..].to_string(),
+                    output_byte_to_input_byte: (start + keep_from..end).map(Some).collect(),
+                    moved_delim_len: 0,
+                })
+            }
+            _ => collapse_run(source, start, end, false),
+        };
+        edits.extend(edit.map(|edit| PlannedEdit::new(edit, EditKind::CollapsedAttributeGap)));
     }
+}
 
-    (
-        String::from_utf8(replacement).expect("replacement must be utf-8"),
-        input_offset_for_output,
+/// Returns an edit collapsing the ASCII whitespace run `source[start..end]` to a single space,
+/// or with `keep_newline` to a single line break if the run contains one. Returns `None` if the
+/// run is already collapsed.
+///
+/// The replacement maps back to the first byte of the run, or to the line break it keeps.
+fn collapse_run(source: &str, start: usize, end: usize, keep_newline: bool) -> Option<Edit> {
+    let run = &source[start..end];
+    let newline = run.find('\n').filter(|_| keep_newline);
+    let (replacement, output_byte_to_input_byte) = match newline {
+        Some(nl) if nl > 0 && run[..nl].ends_with('\r') => {
+            ("\r\n", vec![Some(start + nl - 1), Some(start + nl)])
+        }
+        Some(nl) => ("\n", vec![Some(start + nl)]),
+        None => (" ", vec![Some(start)]),
+    };
+    (run != replacement).then(|| Edit {
+        start,
+        end,
+        replacement: replacement.to_string(),
+        output_byte_to_input_byte,
+        moved_delim_len: 0,
+    })
+}
+
+/// Returns true if gaps between children of a `kind` node are rewritten for `language`.
+pub(crate) fn is_container(kind: &str, language: Language) -> bool {
+    matches!(
+        (language, kind),
+        (Language::Astro, "document" | "element")
+            | (
+                Language::Svelte,
+                "document"
+                    | "fragment"
+                    | "element"
+                    | "if_statement"
+                    | "each_statement"
+                    | "await_statement"
+                    | "key_statement"
+                    | "snippet_statement"
+                    | "else_block"
+                    | "else_if_block"
+            )
+            | (Language::Vue, "template_element" | "element")
+            | (Language::Html, "document" | "element")
     )
 }
 
-/// Rotates an opener prefix (e.g. `{`, `<!--`, `<tag`) left across a whitespace `gap`.
-///
-/// Input segment: `gap + prefix`
-/// Output segment: `prefix + gap`
-///
-/// Returns `(replacement, input_offset_for_output)` where each output byte is mapped back to
-/// its originating input offset within the input segment.
-fn rotate_prefix_over_gap(prefix: &[u8], gap: &str) -> (String, Vec<usize>) {
-    // Original segment is: gap + prefix
-    // We want: prefix + gap
-    let gap_bytes = gap.as_bytes();
-    let gap_len = gap_bytes.len();
-    let prefix_len = prefix.len();
+/// Returns the first `ERROR` or `MISSING` node under `root` in document order.
+fn first_syntax_error(root: tree_sitter::Node<'_>) -> Option<tree_sitter::Node<'_>> {
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_error() || node.is_missing() {
+            return Some(node);
+        }
 
-    let mut replacement = Vec::<u8>::with_capacity(gap_len + prefix_len);
-    let mut input_offset_for_output: Vec<usize> = Vec::with_capacity(gap_len + prefix_len);
+        // Only descend into subtrees that contain an error.
+        if node.has_error() && cursor.goto_first_child() {
+            continue;
+        }
+
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return None;
+            }
+        }
+    }
+}
+
+/// Comment directives that opt parts of a file out of stripping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Directive {
+    /// `<!-- strip-whitespace-ignore -->`: protect the next sibling node.
+    Ignore,
+    /// `<!-- strip-whitespace-disable -->`: protect everything up to the next enable directive.
+    Disable,
+    /// `<!-- strip-whitespace-enable -->`: end a disabled range.
+    Enable,
+    /// `<!-- strip-whitespace-ignore-file -->`: leave the whole file untouched.
+    IgnoreFile,
+}
+
+/// Returns true if `source` may contain something that switches stripping off: a [`Directive`]
+/// comment, a [`MARKER_ATTRIBUTE`] or a file-level framework option such as
+/// `<svelte:options preserveWhitespace />` (a cheap textual check).
+pub(crate) fn may_suppress_stripping(source: &str) -> bool {
+    source.contains("strip-whitespace") || source.contains("preserveWhitespace")
+}
+
+impl Directive {
+    /// Parses a directive from a `comment` node, ignoring surrounding whitespace in the comment.
+    fn from_comment(source: &str, comment: tree_sitter::Node<'_>) -> Option<Self> {
+        let text = source.get(comment.start_byte()..comment.end_byte())?;
+        let body = text.strip_prefix("<!--")?.strip_suffix("-->")?.trim();
+        match body {
+            "strip-whitespace-ignore" => Some(Directive::Ignore),
+            "strip-whitespace-disable" => Some(Directive::Disable),
+            "strip-whitespace-enable" => Some(Directive::Enable),
+            "strip-whitespace-ignore-file" => Some(Directive::IgnoreFile),
+            _ => None,
+        }
+    }
+}
+
+/// Trailing delimiters that can be rotated across a whitespace gap.
+///
+/// The delimiter is detected by node kind and then verified against source bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrailingDelim {
+    Gt,
+    SlashGt,
+    CommentEnd,
+    RBrace,
+    DoubleRBrace,
+}
+
+impl TrailingDelim {
+    /// Returns the literal bytes of the delimiter.
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            TrailingDelim::Gt => b">",
+            TrailingDelim::SlashGt => b"/>",
+            TrailingDelim::CommentEnd => b"-->",
+            TrailingDelim::RBrace => b"}",
+            TrailingDelim::DoubleRBrace => b"}}",
+        }
+    }
+
+    /// Returns the delimiter length in bytes.
+    fn len(self) -> usize {
+        self.bytes().len()
+    }
+
+    /// Attempts to infer the delimiter type that ends `node`.
+    ///
+    /// For `element`, we inspect its last named child to find the real trailing token.
+    fn from_node(node: tree_sitter::Node<'_>, language: Language) -> Option<Self> {
+        match (language, node.kind()) {
+            (_, "start_tag" | "end_tag") => Some(TrailingDelim::Gt),
+            (_, "self_closing_tag") => Some(TrailingDelim::SlashGt),
+            (_, "comment") => Some(TrailingDelim::CommentEnd),
+            (Language::Astro, "html_interpolation") => Some(TrailingDelim::RBrace),
+            (Language::Svelte, "expression") => Some(TrailingDelim::RBrace),
+            // Svelte tags: `{@html}`, `{@render}`, `{@const}`, `{@debug}`
+            (Language::Svelte, "html_tag" | "render_tag" | "const_tag" | "debug_tag") => {
+                Some(TrailingDelim::RBrace)
+            }
+            (Language::Vue, "interpolation") => Some(TrailingDelim::DoubleRBrace),
+            (Language::Html, "doctype") => Some(TrailingDelim::Gt),
+            // Svelte control flow blocks end with }
+            (
+                Language::Svelte,
+                "if_start" | "else_if_start" | "if_end" | "each_start" | "each_end" | "await_start"
+                | "await_end" | "key_start" | "key_end" | "snippet_start" | "snippet_end"
+                | "else_start",
+            ) => Some(TrailingDelim::RBrace),
+            (_, "element")
+            | (Language::Vue, "template_element")
+            | (Language::Html, "script_element" | "style_element") => {
+                // Find the actual trailing delimiter from the element's last child.
+                let mut cursor = node.walk();
+                let last_child = node.named_children(&mut cursor).last()?;
+                TrailingDelim::from_node(last_child, language)
+            }
+            // Svelte statements end with their end tags
+            (
+                Language::Svelte,
+                "if_statement" | "each_statement" | "await_statement" | "key_statement"
+                | "snippet_statement",
+            ) => {
+                // Find the actual trailing delimiter from the statement's last child.
+                let mut cursor = node.walk();
+                let last_child = node.named_children(&mut cursor).last()?;
+                TrailingDelim::from_node(last_child, language)
+            }
+            // else_block ends with its last child's delimiter
+            (Language::Svelte, "else_block" | "else_if_block") => {
+                let mut cursor = node.walk();
+                let last_child = node.named_children(&mut cursor).last()?;
+                TrailingDelim::from_node(last_child, language)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns true if `node` is an element listed in
+/// [`StripConfig::whitespace_sensitive_elements`], whose subtree is never rewritten.
+pub(crate) fn is_whitespace_sensitive_element(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> bool {
+    node.kind() == "element"
+        && element_tag_name(source, node)
+            .is_some_and(|tag_name| config.is_whitespace_sensitive(tag_name, language))
+}
+
+/// How the containers in a subtree are rewritten, as set by markers, tag rules and
+/// [`svelte_special_element_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SubtreeMode {
+    Strip,
+    Off,
+    CollapseToSpace,
+    /// Whitespace in the subtree never renders, so gaps are stripped even where
+    /// [`StripConfig::preserve_blank_lines`] or [`StripConfig::inline_safe`] would keep them.
+    Unrendered,
+}
+
+/// Returns the mode for the contents of the Svelte special element `node`, or `None` if it
+/// follows the normal element rules.
+fn svelte_special_element_mode(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+) -> Option<SubtreeMode> {
+    if language != Language::Svelte || node.kind() != "element" {
+        return None;
+    }
+    match element_tag_name(source, node)? {
+        // Svelte moves the contents into `document.head`, where whitespace never renders.
+        "svelte:head" => Some(SubtreeMode::Unrendered),
+        // Children of the other special elements render in place, like a normal element's:
+        // `svelte:boundary` and `svelte:fragment` add no element of their own, `svelte:element`
+        // adds one whose tag is only known at runtime. `svelte:window`, `svelte:document` and
+        // `svelte:body` take no children.
+        _ => None,
+    }
+}
+
+/// Returns true if `source` may contain a `<svelte:head` tag, whose contents are not stripped
+/// like a normal element's (a cheap textual check).
+pub(crate) fn may_contain_svelte_head(source: &str, language: Language) -> bool {
+    language == Language::Svelte && source.contains("<svelte:head")
+}
+
+/// Returns the action of the tag rule matching the element `node`, if any.
+fn element_action(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> Option<TagAction> {
+    if config.tag_rules.is_empty() || node.kind() != "element" {
+        return None;
+    }
+    config.tag_action(element_tag_name(source, node)?, language)
+}
+
+/// Returns true if a tag rule keeps the gaps around the element `node` untouched.
+fn keeps_surrounding_gaps(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> bool {
+    matches!(
+        element_action(source, node, language, config),
+        Some(TagAction::Preserve | TagAction::StripChildren)
+    )
+}
+
+/// Returns true if `node` is an Astro element with the `is:raw` directive, whose children Astro
+/// renders verbatim.
+fn is_raw_element(source: &str, node: tree_sitter::Node<'_>, language: Language) -> bool {
+    language == Language::Astro
+        && node.kind() == "element"
+        && element_attribute(source, node, "is:raw").is_some()
+}
+
+/// Returns true if the walk never descends into `node`: a whitespace-sensitive element or an
+/// Astro `is:raw` element.
+pub(crate) fn is_opaque_element(
+    source: &str,
+    node: tree_sitter::Node<'_>,
+    language: Language,
+    config: &StripConfig,
+) -> bool {
+    is_whitespace_sensitive_element(source, node, language, config)
+        || is_raw_element(source, node, language)
+}
+
+/// Returns true if a top-level `<svelte:options>` element under `root` sets `preserveWhitespace`
+/// (bare, `{true}` or `"true"`).
+fn svelte_preserves_whitespace(source: &str, root: tree_sitter::Node<'_>) -> bool {
+    let mut cursor = root.walk();
+    root.named_children(&mut cursor)
+        .filter(|c| c.kind() == "element" && element_tag_name(source, *c) == Some("svelte:options"))
+        .filter_map(|options| element_attribute(source, options, "preserveWhitespace"))
+        .any(|attr| attribute_value(source, attr).is_none_or(|value| value == "true"))
+}
+
+/// Returns the `attribute` node called `name` on the start tag or self-closing tag of `element`.
+fn element_attribute<'t>(
+    source: &str,
+    element: tree_sitter::Node<'t>,
+    name: &str,
+) -> Option<tree_sitter::Node<'t>> {
+    let mut cursor = element.walk();
+    let tag = element
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "start_tag" || c.kind() == "self_closing_tag")?;
+    let mut cursor = tag.walk();
+    tag.named_children(&mut cursor)
+        .find(|&attr| attribute_name(source, attr) == Some(name))
+}
+
+/// Returns the name of an `attribute` node, or `None` for any other node.
+fn attribute_name<'s>(source: &'s str, attr: tree_sitter::Node<'_>) -> Option<&'s str> {
+    if attr.kind() != "attribute" {
+        return None;
+    }
+    let mut cursor = attr.walk();
+    let name = attr
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "attribute_name")?;
+    source.get(name.start_byte()..name.end_byte())
+}
+
+/// Returns an edit deleting the `attribute` node `attr` and the whitespace before it.
+fn remove_attribute(source: &str, attr: tree_sitter::Node<'_>) -> Edit {
+    let bytes = source.as_bytes();
+    let mut start = attr.start_byte();
+    while start > 0 && bytes[start - 1].is_ascii_whitespace() {
+        start -= 1;
+    }
+    Edit {
+        start,
+        end: attr.end_byte(),
+        replacement: String::new(),
+        output_byte_to_input_byte: Vec::new(),
+        moved_delim_len: 0,
+    }
+}
+
+/// Returns the value of an `attribute` node without its quotes or `{}`, or `None` if the
+/// attribute has no value.
+fn attribute_value<'s>(source: &'s str, attr: tree_sitter::Node<'_>) -> Option<&'s str> {
+    let (_, value) = source
+        .get(attr.start_byte()..attr.end_byte())?
+        .split_once('=')?;
+    let value = value.trim();
+    let unwrapped = [('"', '"'), ('\'', '\''), ('{', '}')]
+        .iter()
+        .find_map(|&(open, close)| value.strip_prefix(open)?.strip_suffix(close));
+    Some(unwrapped.unwrap_or(value).trim())
+}
+
+/// Returns the tag name of an `element` node, taken from its start tag or self-closing tag.
+fn element_tag_name<'s>(source: &'s str, element: tree_sitter::Node<'_>) -> Option<&'s str> {
+    let mut cursor = element.walk();
+    let tag = element
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "start_tag" || c.kind() == "self_closing_tag")?;
+    let mut cursor = tag.walk();
+    let tag_name = tag
+        .named_children(&mut cursor)
+        .find(|c| c.kind() == "tag_name")?;
+    source.get(tag_name.start_byte()..tag_name.end_byte())
+}
+
+/// Returns the byte offset of the end of the "opener prefix" for `next`.
+///
+/// This is used for the "rotate opener prefix left" transformation.
+///
+/// Examples (prefixes moved):
+///
+/// - `comment`: `<!--`
+/// - `html_interpolation` (Astro) / `expression` (Svelte): `{`
+/// - `{@...}` tags and `{#...}`/`{:...}`/`{/...}` block tags (Svelte): `{` plus the keyword, e.g.
+///   `{@render` or `{#if`
+/// - `interpolation` (Vue): `{{`
+/// - `start_tag`/`end_tag`/`self_closing_tag`: `<tagname` (up through the `tag_name` node)
+fn opener_prefix_end(
+    source: &str,
+    next: tree_sitter::Node<'_>,
+    language: Language,
+) -> Option<usize> {
+    let start = next.start_byte();
+    let bytes = source.as_bytes();
+
+    match (language, next.kind()) {
+        // Astro uses "html_interpolation", Svelte uses "expression"
+        (Language::Astro, "html_interpolation") | (Language::Svelte, "expression") => {
+            if bytes.get(start) == Some(&b'{') {
+                Some(start + 1)
+            } else {
+                None
+            }
+        }
+        (Language::Vue, "interpolation") => {
+            if bytes.get(start..start + 2) == Some(b"{{") {
+                Some(start + 2)
+            } else {
+                None
+            }
+        }
+        // Svelte control flow blocks (`{#if}`, `{:else}`, `{/if}`, etc.) and `{@...}` tags: move
+        // the keyword along with the "{" so that no whitespace ends up between them.
+        (
+            Language::Svelte,
+            "if_start" | "else_if_start" | "else_start" | "if_end" | "each_start" | "each_end"
+            | "await_start" | "await_end" | "key_start" | "key_end" | "snippet_start"
+            | "snippet_end" | "html_tag" | "render_tag" | "const_tag" | "debug_tag",
+        ) => {
+            if bytes.get(start) != Some(&b'{') {
+                return None;
+            }
+            let mut cursor = next.walk();
+            let keyword = next.named_children(&mut cursor).find(|c| {
+                matches!(
+                    c.kind(),
+                    "block_start_tag" | "block_tag" | "block_end_tag" | "expression_tag"
+                )
+            });
+            Some(keyword.map_or(start + 1, |k| k.end_byte()))
+        }
+        (_, "comment") => {
+            if bytes.get(start..start + 4) == Some(b"<!--") {
+                Some(start + 4)
+            } else {
+                None
+            }
+        }
+        (_, "element")
+        | (Language::Vue, "template_element")
+        | (Language::Html, "script_element" | "style_element") => {
+            // Move "<" + tag_name for the element's start tag.
+            let mut cursor = next.walk();
+            let tag = next
+                .named_children(&mut cursor)
+                .find(|c| c.kind() == "start_tag" || c.kind() == "self_closing_tag")?;
+            opener_prefix_end(source, tag, language)
+        }
+        // Svelte statements: move the opening "{" from the first child
+        (
+            Language::Svelte,
+            "if_statement" | "each_statement" | "await_statement" | "key_statement"
+            | "snippet_statement",
+        ) => {
+            let mut cursor = next.walk();
+            let first_child = next.named_children(&mut cursor).next()?;
+            opener_prefix_end(source, first_child, language)
+        }
+        (_, "start_tag" | "end_tag" | "self_closing_tag") => {
+            if bytes.get(start) != Some(&b'<') {
+                return None;
+            }
+            let mut cursor = next.walk();
+            let tag_name = next
+                .named_children(&mut cursor)
+                .find(|c| c.kind() == "tag_name")?;
+            if tag_name.end_byte() > start {
+                Some(tag_name.end_byte())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if a whitespace-only gap contains a "blank line".
+///
+/// A blank line is defined as two consecutive line breaks (`\n\n` or `\r\n\r\n`).
+fn contains_blank_line(ws: &str) -> bool {
+    // Handles both LF and CRLF. We treat an empty line as any occurrence of
+    // two consecutive line breaks in the whitespace gap.
+    let b = ws.as_bytes();
+    if b.windows(2).any(|w| w == b"\n\n") {
+        return true;
+    }
+    if b.windows(4).any(|w| w == b"\r\n\r\n") {
+        return true;
+    }
+    false
+}
+
+/// Rotates a trailing delimiter from `prev` across the whitespace `gap`.
+///
+/// Input segment: `delim + gap`
+/// Output segment: `(optional stolen indent) + gap' + delim`
+///
+/// Returns `(replacement, input_offset_for_output)` where each output byte is mapped back to
+/// its originating input offset within the input segment.
+fn rotate_delim_over_gap(delim: TrailingDelim, gap: &str) -> (String, Vec<usize>) {
+    // Original segment is: delim + gap
+    // We want: (optional stolen indent) + gap' + delim
+    // Where stolen indent is one space/tab "stolen" from the end of indentation on the line
+    // where the next node begins, to preserve column numbers when possible.
+
+    let delim_bytes = delim.bytes();
+    let delim_len = delim_bytes.len();
+
+    let gap_bytes = gap.as_bytes();
+    let gap_len = gap_bytes.len();
+
+    // Find the start of the final line within `gap`.
+    let mut last_line_start = 0usize;
+    for (i, &b) in gap_bytes.iter().enumerate() {
+        if b == b'\n' {
+            last_line_start = i + 1;
+        }
+    }
+
+    // Identify "stealable" whitespace bytes on that final line.
+    // For "/>" we steal up to 2 bytes to preserve the next node's column.
+    // For other delimiters, keep the historical behavior (steal at most 1 byte).
+    let max_steal = match delim {
+        TrailingDelim::SlashGt => 2,
+        _ => 1,
+    };
+
+    let mut steal_indices: Vec<usize> = Vec::new();
+    if max_steal > 0 && last_line_start < gap_len {
+        let mut i = gap_len;
+        while i > last_line_start && steal_indices.len() < max_steal {
+            let b = gap_bytes[i - 1];
+            if matches!(b, b' ' | b'\t') {
+                steal_indices.push(i - 1);
+                i -= 1;
+            } else {
+                break;
+            }
+        }
+        steal_indices.sort_unstable();
+    }
+
+    let mut replacement = Vec::<u8>::with_capacity(delim_len + gap_len);
+    let mut input_offset_for_output: Vec<usize> = Vec::with_capacity(delim_len + gap_len);
+
+    // Source segment indices:
+    // - delim is at input offsets 0..delim_len
+    // - gap bytes are at input offsets delim_len..(delim_len + gap_len)
+
+    if !steal_indices.is_empty() {
+        // Output stolen bytes first.
+        for &steal_i in &steal_indices {
+            replacement.push(gap_bytes[steal_i]);
+            input_offset_for_output.push(delim_len + steal_i);
+        }
+
+        // Then output the gap minus the stolen bytes.
+        for (i, &b) in gap_bytes.iter().enumerate() {
+            if steal_indices.binary_search(&i).is_ok() {
+                continue;
+            }
+            replacement.push(b);
+            input_offset_for_output.push(delim_len + i);
+        }
+
+        // And finally the delimiter bytes.
+        for (i, &b) in delim_bytes.iter().enumerate() {
+            replacement.push(b);
+            input_offset_for_output.push(i);
+        }
+
+        return (
+            String::from_utf8(replacement).expect("replacement must be utf-8"),
+            input_offset_for_output,
+        );
+    }
+
+    // No indentation to steal; just rotate: gap + delim.
+    for (i, &b) in gap_bytes.iter().enumerate() {
+        replacement.push(b);
+        input_offset_for_output.push(delim_len + i);
+    }
+    for (i, &b) in delim_bytes.iter().enumerate() {
+        replacement.push(b);
+        input_offset_for_output.push(i);
+    }
+
+    (
+        String::from_utf8(replacement).expect("replacement must be utf-8"),
+        input_offset_for_output,
+    )
+}
+
+/// Rotates an opener prefix (e.g. `{`, `<!--`, `<tag`) left across a whitespace `gap`.
+///
+/// Input segment: `gap + prefix`
+/// Output segment: `prefix + gap`
+///
+/// Returns `(replacement, input_offset_for_output)` where each output byte is mapped back to
+/// its originating input offset within the input segment.
+fn rotate_prefix_over_gap(prefix: &[u8], gap: &str) -> (String, Vec<usize>) {
+    // Original segment is: gap + prefix
+    // We want: prefix + gap
+    let gap_bytes = gap.as_bytes();
+    let gap_len = gap_bytes.len();
+    let prefix_len = prefix.len();
+
+    let mut replacement = Vec::<u8>::with_capacity(gap_len + prefix_len);
+    let mut input_offset_for_output: Vec<usize> = Vec::with_capacity(gap_len + prefix_len);
+
+    // Input segment indices:
+    // - gap is at input offsets 0..gap_len
+    // - prefix is at input offsets gap_len..(gap_len+prefix_len)
+
+    // Output prefix first.
+    for (i, &b) in prefix.iter().enumerate() {
+        replacement.push(b);
+        input_offset_for_output.push(gap_len + i);
+    }
+
+    // Then output the original gap.
+    for (i, &b) in gap_bytes.iter().enumerate() {
+        replacement.push(b);
+        input_offset_for_output.push(i);
+    }
+
+    (
+        String::from_utf8(replacement).expect("replacement must be utf-8"),
+        input_offset_for_output,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::DelimRotations;
+
+    /// Strips whitespace using the default config for Astro.
+    fn strip_astro(src: &str) -> String {
+        strip_whitespace_no_sourcemap(src, Language::Astro, &StripConfig::default()).unwrap()
+    }
+
+    /// Strips whitespace using a custom `preserve_blank_lines` setting for Astro.
+    fn strip_astro_cfg(src: &str, preserve_blank_lines: bool) -> String {
+        let cfg = StripConfig {
+            preserve_blank_lines,
+            ..Default::default()
+        };
+        strip_whitespace_no_sourcemap(src, Language::Astro, &cfg).unwrap()
+    }
+
+    /// Strips whitespace using the default config for Svelte.
+    fn strip_svelte(src: &str) -> String {
+        strip_whitespace_no_sourcemap(src, Language::Svelte, &StripConfig::default()).unwrap()
+    }
+
+    /// Strips whitespace using a custom `preserve_blank_lines` setting for Svelte.
+    fn strip_svelte_cfg(src: &str, preserve_blank_lines: bool) -> String {
+        let cfg = StripConfig {
+            preserve_blank_lines,
+            ..Default::default()
+        };
+        strip_whitespace_no_sourcemap(src, Language::Svelte, &cfg).unwrap()
+    }
+
+    /// Strips whitespace for both Astro and Svelte, asserting they produce the same result.
+    /// Use this for tests where behavior should be identical across languages.
+    fn strip_all(src: &str) -> String {
+        let astro_out = strip_astro(src);
+        let svelte_out = strip_svelte(src);
+        assert_eq!(
+            astro_out, svelte_out,
+            "Astro (left) and Svelte (right) produced different outputs for the same input with config.\n input: {src:?}"
+        );
+        astro_out
+    }
+
+    /// Strips whitespace for both languages with config, asserting they produce the same result.
+    fn strip_all_cfg(src: &str, preserve_blank_lines: bool) -> String {
+        let astro_out = strip_astro_cfg(src, preserve_blank_lines);
+        let svelte_out = strip_svelte_cfg(src, preserve_blank_lines);
+        assert_eq!(
+            astro_out, svelte_out,
+            "Astro (left) and Svelte (right) produced different outputs for the same input with config.\n input: {src:?}"
+        );
+        astro_out
+    }
+
+    /// Asserts that `map` is a permutation of `0..map.len()`.
+    fn assert_is_permutation(map: &[usize]) {
+        let mut v = map.to_vec();
+        v.sort_unstable();
+        v.dedup();
+        assert_eq!(v, (0..map.len()).collect::<Vec<_>>());
+    }
+
+    /// Rotates `>` over a newline-only gap.
+    #[test]
+    fn rotates_gt_over_newline_only() {
+        let (out, map) = rotate_delim_over_gap(TrailingDelim::Gt, "\n");
+        assert_eq!(out, "\n>");
+        assert_eq!(map.len(), 2);
+        assert_is_permutation(&map);
+    }
+
+    /// Rotates `>` over a newline+indent gap and steals one indent byte.
+    #[test]
+    fn rotates_gt_over_newline_and_indent_with_filler() {
+        let (out, map) = rotate_delim_over_gap(TrailingDelim::Gt, "\n  ");
+        assert_eq!(out, " \n >");
+        assert_eq!(map.len(), 4);
+        assert_is_permutation(&map);
+    }
+
+    /// Rotates `-->` over a newline+indent gap and steals one indent byte.
+    #[test]
+    fn rotates_comment_end_over_newline_and_indent_with_filler() {
+        let (out, map) = rotate_delim_over_gap(TrailingDelim::CommentEnd, "\n  ");
+        assert_eq!(out, " \n -->");
+        assert_eq!(map.len(), 6);
+        assert_is_permutation(&map);
+    }
+
+    /// Rotates `/>` over a newline+indent gap and steals up to two indent bytes.
+    #[test]
+    fn rotates_slash_gt_over_newline_and_indent_with_two_fillers() {
+        let (out, map) = rotate_delim_over_gap(TrailingDelim::SlashGt, "\n  ");
+        // Steal both indentation spaces so the next node keeps its original column.
+        assert_eq!(out, "  \n/>");
+        assert_eq!(map.len(), 5);
+        assert_is_permutation(&map);
+    }
+
+    /// Creates a sourcemap (Astro).
+    #[test]
+    fn astro_emits_sourcemap() {
+        let src = "<div>\n  <span>ok</span>\n</div>\n";
+        let res =
+            strip_whitespace(src, "input.astro", Language::Astro, &StripConfig::default()).unwrap();
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        assert_eq!(sm.get_source(0), Some("input.astro"));
+    }
+
+    /// Creates a sourcemap (Svelte).
+    #[test]
+    fn svelte_emits_sourcemap() {
+        let src = "<div>\n  <span>ok</span>\n</div>\n";
+        let res = strip_whitespace(
+            src,
+            "input.svelte",
+            Language::Svelte,
+            &StripConfig::default(),
+        )
+        .unwrap();
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        assert_eq!(sm.get_source(0), Some("input.svelte"));
+    }
+
+    /// Rewrites an input sourcemap so it points at the original file.
+    #[test]
+    fn rewrites_input_sourcemap() {
+        // Pretend an upstream tool prepended two lines: every byte of `src` maps to the same
+        // column two lines further down in "original.svelte".
+        let src = "<div>\n  <span>ok</span>\n</div>\n";
+        let mut builder = sourcemap::SourceMapBuilder::new(None);
+        for (line, text) in src.lines().enumerate() {
+            for col in 0..text.len() as u32 {
+                builder.add(
+                    line as u32,
+                    col,
+                    line as u32 + 2,
+                    col,
+                    Some("original.svelte"),
+                    None,
+                    false,
+                );
+            }
+        }
+        let mut input_map: Vec<u8> = Vec::new();
+        builder.into_sourcemap().to_writer(&mut input_map).unwrap();
+        let input_map = String::from_utf8(input_map).unwrap();
+
+        let res = strip_whitespace_with_input_map(
+            src,
+            &input_map,
+            Language::Svelte,
+            &StripConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(res.code, strip_svelte(src));
+
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        assert_eq!(sm.get_source(0), Some("original.svelte"));
+
+        // "<span" starts at line 1 col 2 in the output, which is line 3 col 2 in the original.
+        let t = sm.lookup_token(1, 2).expect("token for '<span'");
+        assert_eq!(t.get_source(), Some("original.svelte"));
+        assert_eq!(t.get_src_line(), 3);
+        assert_eq!(t.get_src_col(), 2);
+
+        // The moved '>' of "<div>" (line 1 col 1 in the output) maps to line 2 col 4.
+        let t = sm.lookup_token(1, 1).expect("token for moved '>'");
+        assert_eq!(t.get_src_line(), 2);
+        assert_eq!(t.get_src_col(), 4);
+    }
+
+    /// Planned edits reproduce the stripped output when applied.
+    #[test]
+    fn plan_edits_then_apply_matches_strip() {
+        let src = "<div>\n  <span>ok</span>\n  text\n</div>\n";
+        for language in [Language::Astro, Language::Svelte] {
+            let edits = plan_edits(src, language, &StripConfig::default()).unwrap();
+            assert!(!edits.is_empty());
+            assert!(edits.windows(2).all(|w| w[0].end <= w[1].start));
+
+            let out = apply_edits(src, &edits).unwrap();
+            let expected =
+                strip_whitespace_no_sourcemap(src, language, &StripConfig::default()).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+
+    /// The report counts examined gaps, rotations by kind, and whitespace bytes rewritten.
+    #[test]
+    fn report_counts_rotations_and_skipped_gaps() {
+        let src = "<div>\n  <span>ok</span>\n  text\n\n  <!-- c -->\n  <br/>\n</div>";
+
+        let (_, report) =
+            strip_whitespace_with_report(src, Language::Astro, &StripConfig::default()).unwrap();
+        assert_eq!(
+            report,
+            StripReport {
+                gaps_examined: 5,
+                delim_rotations: DelimRotations {
+                    gt: 2,
+                    slash_gt: 1,
+                    comment_end: 1,
+                    ..Default::default()
+                },
+                prefix_rotations: 1,
+                skipped_blank_lines: 0,
+                collapsed_text_runs: 0,
+                trimmed_block_text: 0,
+                inline_gaps: 0,
+                removed_marker_attributes: 0,
+                deleted_gaps: 0,
+                custom_gaps: 0,
+                collapsed_attribute_gaps: 0,
+                removed_comments: 0,
+                suppressions: Default::default(),
+                estimated_bytes_saved: 14,
+            }
+        );
+
+        let cfg = StripConfig {
+            preserve_blank_lines: true,
+            ..Default::default()
+        };
+        let out = strip_whitespace(src, "input.astro", Language::Astro, &cfg).unwrap();
+        assert_eq!(out.report.gaps_examined, 5);
+        assert_eq!(out.report.skipped_blank_lines, 1);
+        assert_eq!(out.report.prefix_rotations, 0);
+        assert_eq!(out.report.delim_rotations.total(), 4);
+        assert_eq!(out.report.estimated_bytes_saved, 10);
+    }
+
+    /// Edits dropped by a directive are not counted as rotations.
+    #[test]
+    fn report_excludes_edits_dropped_by_directives() {
+        let src = "<div>\n  <!-- strip-whitespace-ignore -->\n  <p>\n    keep\n  </p>\n</div>";
+        let (out, report) =
+            strip_whitespace_with_report(src, Language::Astro, &StripConfig::default()).unwrap();
+        let edits = plan_edits(src, Language::Astro, &StripConfig::default()).unwrap();
+        assert_ne!(out, src);
+        assert_eq!(
+            report.delim_rotations.total() + report.prefix_rotations,
+            edits.len()
+        );
+    }
+
+    /// Deleted gaps next to inline text are counted as gaps, not as trimmed block text.
+    #[test]
+    fn report_counts_deleted_gaps_and_trimmed_text_separately() {
+        let src = "<div>\n  <p>\n    Hello\n  </p>\n  <span>a</span> text\n</div>";
+        let cfg = StripConfig {
+            gap_strategy: GapStrategy::Delete,
+            trim_block_text: true,
+            ..Default::default()
+        };
+        let (out, report) = strip_whitespace_with_report(src, Language::Html, &cfg).unwrap();
+        assert_eq!(out, "<div><p>Hello</p><span>a</span>text</div>");
+        assert_eq!((report.trimmed_block_text, report.deleted_gaps), (3, 3));
+        assert_eq!(report.estimated_bytes_saved, src.len() - out.len());
+    }
+
+    /// Rotates an opener prefix (`{`) left over a gap.
+    #[test]
+    fn rotates_prefix_left_over_gap() {
+        let (out, map) = rotate_prefix_over_gap(b"{", "\n  ");
+        assert_eq!(out, "{\n  ");
+        assert_eq!(map.len(), 4);
+        assert_is_permutation(&map);
+    }
+
+    /// Detects blank lines in LF and CRLF whitespace.
+    #[test]
+    fn contains_blank_line_lf_and_crlf() {
+        assert!(contains_blank_line("\n\n"));
+        assert!(contains_blank_line(" \n\n  "));
+        assert!(contains_blank_line("\r\n\r\n"));
+        assert!(!contains_blank_line("\n  \n"));
+    }
+
+    /// End-tag delimiter rotation steals indentation when available (both languages).
+    #[test]
+    fn rewrite_rotates_gt_before_text_with_indent_steal() {
+        let src = "<span>\n  text</span>";
+        let out = strip_all(src);
+        assert_eq!(out, "<span \n >text</span>");
+    }
+
+    /// End-tag delimiter rotation without indentation (both languages).
+    #[test]
+    fn rewrite_rotates_gt_before_text_without_indent() {
+        let src = "<span>\ntext</span>";
+        let out = strip_all(src);
+        assert_eq!(out, "<span\n>text</span>");
+    }
+
+    /// Interpolation `}` delimiter rotation.
+    #[test]
+    fn rewrite_rotates_rbrace_before_next_node() {
+        let src = "{a}\n  <b/>";
+        let out = strip_all(src);
+        assert_eq!(out, "{a \n }<b/>");
+    }
+
+    /// Comment end delimiter (`-->`) rotation does not corrupt comment bytes.
+    #[test]
+    fn rewrite_rotates_comment_end_before_next_node() {
+        let src = "<!--c-->\n  <span/>";
+        let out = strip_all(src);
+        assert_eq!(out, "<!--c \n --><span/>");
+    }
+
+    /// `<tagname` opener prefix rotation after text.
+    #[test]
+    fn rewrite_rotates_tag_prefix_left_after_text() {
+        let src = "hi\n  <span/>";
+        let out = strip_all(src);
+        assert_eq!(out, "hi<span\n  />");
+    }
+
+    /// `<!--` opener prefix rotation after text.
+    #[test]
+    fn rewrite_rotates_comment_prefix_left_after_text() {
+        let src = "hi\n  <!--c-->";
+        let out = strip_all(src);
+        assert_eq!(out, "hi<!--\n  c-->");
+    }
+
+    /// `{` opener prefix rotation after text.
+    #[test]
+    fn rewrite_rotates_interpolation_prefix_left_after_text() {
+        let src = "hi\n  {a}";
+        let out = strip_all(src);
+        assert_eq!(out, "hi{\n  a}");
+    }
+
+    /// By default, blank-line gaps are not preserved.
+    #[test]
+    fn rewrite_does_not_preserve_blank_line_gaps_by_default() {
+        let src = "<a></a>\n\n<b/>";
+        let out = strip_all(src);
+        assert_ne!(out, src);
+
+        let src_crlf = "<a></a>\r\n\r\n<b/>";
+        let out_crlf = strip_all(src_crlf);
+        assert_ne!(out_crlf, src_crlf);
+    }
+
+    /// With config enabled, blank-line gaps are preserved.
+    #[test]
+    fn rewrite_preserves_blank_line_gaps_with_config() {
+        let src = "<a></a>\n\n<b/>";
+        let out = strip_all_cfg(src, true);
+        assert_eq!(out, src);
+
+        let src_crlf = "<a></a>\r\n\r\n<b/>";
+        let out_crlf = strip_all_cfg(src_crlf, true);
+        assert_eq!(out_crlf, src_crlf);
+    }
+
+    /// Whitespace inside `{ ... }` interpolation expressions is preserved (both languages).
+    #[test]
+    fn rewrite_does_not_touch_whitespace_inside_interpolation_expression() {
+        let src = "{ a +  b }";
+        let out = strip_all(src);
+        assert_eq!(out, src);
+    }
+
+    /// Delimiter rotation when an end tag is followed by a start tag.
+    #[test]
+    fn end_tag_then_start_tag() {
+        let src = "<a>x</a>\n  <b></b>";
+        let out = strip_all(src);
+        assert_eq!(out, "<a>x</a \n ><b></b>");
+    }
+
+    /// Delimiter rotation when an end tag is followed by a self-closing tag.
+    #[test]
+    fn end_tag_then_self_closing_tag() {
+        let src = "<a>x</a>\n  <b/>";
+        let out = strip_all(src);
+        assert_eq!(out, "<a>x</a \n ><b/>");
+    }
+
+    /// Delimiter rotation when an end tag is followed by interpolation.
+    #[test]
+    fn end_tag_then_start_interpolation() {
+        let src = "<a>x</a>\n  {b}";
+        let out = strip_all(src);
+        assert_eq!(out, "<a>x</a \n >{b}");
+    }
+
+    /// Delimiter rotation when an end tag is followed by text.
+    #[test]
+    fn end_tag_then_text() {
+        let src = "<a>x</a>\n  text";
+        let out = strip_all(src);
+        assert_eq!(out, "<a>x</a \n >text");
+    }
+
+    /// `/>` delimiter rotation when followed by a start tag.
+    #[test]
+    fn self_closing_tag_then_start_tag() {
+        let src = "<a/>\n  <b>y</b>";
+        let out = strip_all(src);
+        assert_eq!(out, "<a  \n/><b>y</b>");
+    }
 
-    // Input segment indices:
-    // - gap is at input offsets 0..gap_len
-    // - prefix is at input offsets gap_len..(gap_len+prefix_len)
+    /// `/>` delimiter rotation when followed by another self-closing tag.
+    #[test]
+    fn self_closing_tag_then_self_closing_tag() {
+        let src = "<a/>\n  <b/>";
+        let out = strip_all(src);
+        assert_eq!(out, "<a  \n/><b/>");
+    }
 
-    // Output prefix first.
-    for (i, &b) in prefix.iter().enumerate() {
-        replacement.push(b);
-        input_offset_for_output.push(gap_len + i);
+    /// `/>` delimiter rotation when followed by interpolation.
+    #[test]
+    fn self_closing_tag_then_start_interpolation() {
+        let src = "<a/>\n  {b}";
+        let out = strip_all(src);
+        assert_eq!(out, "<a  \n/>{b}");
     }
 
-    // Then output the original gap.
-    for (i, &b) in gap_bytes.iter().enumerate() {
-        replacement.push(b);
-        input_offset_for_output.push(i);
+    /// `/>` delimiter rotation when followed by text.
+    #[test]
+    fn self_closing_tag_then_text() {
+        let src = "<a/>\n  text";
+        let out = strip_all(src);
+        assert_eq!(out, "<a  \n/>text");
     }
 
-    (
-        String::from_utf8(replacement).expect("replacement must be utf-8"),
-        input_offset_for_output,
-    )
-}
+    /// `}` delimiter rotation when interpolation is followed by a start tag.
+    #[test]
+    fn end_interpolation_then_start_tag() {
+        let src = "{a}\n  <b>y</b>";
+        let out = strip_all(src);
+        assert_eq!(out, "{a \n }<b>y</b>");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// `}` delimiter rotation when interpolation is followed by a self-closing tag.
+    #[test]
+    fn end_interpolation_then_self_closing_tag() {
+        let src = "{a}\n  <b/>";
+        let out = strip_all(src);
+        assert_eq!(out, "{a \n }<b/>");
+    }
 
-    /// Strips whitespace using the default config for Astro.
-    fn strip_astro(src: &str) -> String {
-        strip_whitespace_no_sourcemap(src, Language::Astro, &StripConfig::default()).unwrap()
+    /// `}` delimiter rotation when interpolation is followed by interpolation.
+    #[test]
+    fn end_interpolation_then_start_interpolation() {
+        let src = "{a}\n  {b}";
+        let out = strip_all(src);
+        assert_eq!(out, "{a \n }{b}");
     }
 
-    /// Strips whitespace using a custom `preserve_blank_lines` setting for Astro.
-    fn strip_astro_cfg(src: &str, preserve_blank_lines: bool) -> String {
-        let cfg = StripConfig {
-            preserve_blank_lines,
-        };
-        strip_whitespace_no_sourcemap(src, Language::Astro, &cfg).unwrap()
+    /// `}` delimiter rotation when interpolation is followed by text.
+    #[test]
+    fn end_interpolation_then_text() {
+        let src = "{a}\n  text";
+        let out = strip_all(src);
+        assert_eq!(out, "{a \n }text");
     }
 
-    /// Strips whitespace using the default config for Svelte.
-    fn strip_svelte(src: &str) -> String {
-        strip_whitespace_no_sourcemap(src, Language::Svelte, &StripConfig::default()).unwrap()
+    /// Opener prefix rotation when text is followed by a start tag.
+    #[test]
+    fn text_then_start_tag() {
+        let src = "hi\n  <b>y</b>";
+        let out = strip_all(src);
+        assert_eq!(out, "hi<b\n  >y</b>");
     }
 
-    /// Strips whitespace using a custom `preserve_blank_lines` setting for Svelte.
-    fn strip_svelte_cfg(src: &str, preserve_blank_lines: bool) -> String {
-        let cfg = StripConfig {
-            preserve_blank_lines,
-        };
-        strip_whitespace_no_sourcemap(src, Language::Svelte, &cfg).unwrap()
+    /// Opener prefix rotation when text is followed by a self-closing tag.
+    #[test]
+    fn text_then_self_closing_tag() {
+        let src = "hi\n  <b/>";
+        let out = strip_all(src);
+        assert_eq!(out, "hi<b\n  />");
     }
 
-    /// Strips whitespace for both Astro and Svelte, asserting they produce the same result.
-    /// Use this for tests where behavior should be identical across languages.
-    fn strip_all(src: &str) -> String {
-        let astro_out = strip_astro(src);
-        let svelte_out = strip_svelte(src);
-        assert_eq!(
-            astro_out, svelte_out,
-            "Astro (left) and Svelte (right) produced different outputs for the same input with config.\n input: {src:?}"
-        );
-        astro_out
+    /// Whitespace between adjacent text nodes is never modified.
+    #[test]
+    fn text_then_text_do_not_strip() {
+        // Even if tree-sitter ever produces adjacent text nodes, we must not
+        // strip whitespace between them. This is a simple no-op sanity check.
+        let src = "hi\n  there";
+        let out = strip_all(src);
+        assert_eq!(out, src);
     }
 
-    /// Strips whitespace for both languages with config, asserting they produce the same result.
-    fn strip_all_cfg(src: &str, preserve_blank_lines: bool) -> String {
-        let astro_out = strip_astro_cfg(src, preserve_blank_lines);
-        let svelte_out = strip_svelte_cfg(src, preserve_blank_lines);
-        assert_eq!(
-            astro_out, svelte_out,
-            "Astro (left) and Svelte (right) produced different outputs for the same input with config.\n input: {src:?}"
-        );
-        astro_out
+    /// Gaps inside `<pre>` are preserved, while gaps around it are still stripped.
+    #[test]
+    fn pre_contents_are_preserved() {
+        let src = "<div>\n  <pre>\n  <b>x</b>\n  </pre>\n</div>";
+        let out = strip_all(src);
+        assert_eq!(out, "<div \n ><pre>\n  <b>x</b>\n  </pre\n></div>");
     }
 
-    /// Asserts that `map` is a permutation of `0..map.len()`.
-    fn assert_is_permutation(map: &[usize]) {
-        let mut v = map.to_vec();
-        v.sort_unstable();
-        v.dedup();
-        assert_eq!(v, (0..map.len()).collect::<Vec<_>>());
+    /// Whitespace-sensitive elements keep nested content; HTML matches them case-insensitively.
+    #[test]
+    fn textarea_and_listing_contents_are_preserved() {
+        let src = "<textarea>\n  a\n</textarea>";
+        assert_eq!(strip_all(src), src);
+
+        // HTML tag names are case-insensitive.
+        let src = "<TEXTAREA>\n  a\n</TEXTAREA>";
+        let out = strip_whitespace_no_sourcemap(src, Language::Html, &StripConfig::default());
+        assert_eq!(out.unwrap(), src);
+
+        let src = "<listing>\n  <span>\n    a\n  </span>\n</listing>";
+        assert_eq!(strip_all(src), src);
     }
 
-    /// Rotates `>` over a newline-only gap.
+    /// Components named after whitespace-sensitive elements are stripped like any other.
     #[test]
-    fn rotates_gt_over_newline_only() {
-        let (out, map) = rotate_delim_over_gap(TrailingDelim::Gt, "\n");
-        assert_eq!(out, "\n>");
-        assert_eq!(map.len(), 2);
-        assert_is_permutation(&map);
+    fn capitalized_components_are_not_whitespace_sensitive() {
+        for src in [
+            "<Pre>\n  <b>x</b>\n</Pre>",
+            "<Textarea>\n  <b>x</b>\n</Textarea>",
+        ] {
+            assert_ne!(strip_all(src), src);
+        }
     }
 
-    /// Rotates `>` over a newline+indent gap and steals one indent byte.
+    /// Extra whitespace-sensitive elements can be configured.
     #[test]
-    fn rotates_gt_over_newline_and_indent_with_filler() {
-        let (out, map) = rotate_delim_over_gap(TrailingDelim::Gt, "\n  ");
-        assert_eq!(out, " \n >");
-        assert_eq!(map.len(), 4);
-        assert_is_permutation(&map);
+    fn custom_whitespace_sensitive_elements() {
+        let src = "<code>\n  <b>x</b>\n</code>";
+        assert_ne!(strip_all(src), src);
+
+        let mut cfg = StripConfig::default();
+        cfg.whitespace_sensitive_elements.push("code".to_string());
+        for language in [Language::Astro, Language::Svelte] {
+            let out = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+            assert_eq!(out, src);
+        }
     }
 
-    /// Rotates `-->` over a newline+indent gap and steals one indent byte.
+    /// `strip-whitespace-ignore` protects the next node, including the gaps touching it.
     #[test]
-    fn rotates_comment_end_over_newline_and_indent_with_filler() {
-        let (out, map) = rotate_delim_over_gap(TrailingDelim::CommentEnd, "\n  ");
-        assert_eq!(out, " \n -->");
-        assert_eq!(map.len(), 6);
-        assert_is_permutation(&map);
+    fn ignore_directive_protects_next_node() {
+        let src = "<div>\n  <!-- strip-whitespace-ignore -->\n  <nav>\n    <a>x</a>\n  </nav>\n  <p>y</p>\n</div>";
+        let exp = "<div \n ><!-- strip-whitespace-ignore  \n --><nav>\n    <a>x</a>\n  </nav>\n  <p>y</p\n></div>";
+        let out = strip_all(src);
+        assert_eq!(out, exp);
     }
 
-    /// Rotates `/>` over a newline+indent gap and steals up to two indent bytes.
+    /// `strip-whitespace-disable`/`strip-whitespace-enable` protect the range between them.
     #[test]
-    fn rotates_slash_gt_over_newline_and_indent_with_two_fillers() {
-        let (out, map) = rotate_delim_over_gap(TrailingDelim::SlashGt, "\n  ");
-        // Steal both indentation spaces so the next node keeps its original column.
-        assert_eq!(out, "  \n/>");
-        assert_eq!(map.len(), 5);
-        assert_is_permutation(&map);
+    fn disable_enable_directives_protect_range() {
+        let src = "<p>a</p>\n<!-- strip-whitespace-disable -->\n<p>b</p>\n<p>c</p>\n<!-- strip-whitespace-enable -->\n<p>d</p>\n<p>e</p>";
+        let exp = "<p>a</p\n><!-- strip-whitespace-disable -->\n<p>b</p>\n<p>c</p>\n<!-- strip-whitespace-enable \n--><p>d</p\n><p>e</p>";
+        let out = strip_all(src);
+        assert_eq!(out, exp);
+
+        // Without an enable directive, the rest of the file is protected.
+        let src = "<p>a</p>\n<!--strip-whitespace-disable-->\n<p>b</p>\n<p>c</p>";
+        let exp = "<p>a</p\n><!--strip-whitespace-disable-->\n<p>b</p>\n<p>c</p>";
+        assert_eq!(strip_all(src), exp);
     }
 
-    /// Creates a sourcemap (Astro).
+    /// `strip-whitespace-ignore-file` leaves the whole file untouched.
     #[test]
-    fn astro_emits_sourcemap() {
-        let src = "<div>\n  <span>ok</span>\n</div>\n";
-        let res =
-            strip_whitespace(src, "input.astro", Language::Astro, &StripConfig::default()).unwrap();
-        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
-        assert_eq!(sm.get_source(0), Some("input.astro"));
+    fn ignore_file_directive_disables_stripping() {
+        let src = "<div>\n  <p>x</p>\n  <!-- strip-whitespace-ignore-file -->\n  <p>y</p>\n</div>";
+        let out = strip_all(src);
+        assert_eq!(out, src);
+
+        // Gaps walked before the directive are not reported.
+        let (out, report) =
+            strip_whitespace_with_report(src, Language::Html, &StripConfig::default()).unwrap();
+        assert_eq!(out, src);
+        let mut expected = StripReport::default();
+        expected.suppressions.ignore_file = true;
+        assert_eq!(report, expected);
     }
 
-    /// Creates a sourcemap (Svelte).
+    /// `Strict` rejects inputs with syntax errors and reports where the first one is.
     #[test]
-    fn svelte_emits_sourcemap() {
-        let src = "<div>\n  <span>ok</span>\n</div>\n";
-        let res = strip_whitespace(
-            src,
-            "input.svelte",
+    fn strict_syntax_errors_are_rejected() {
+        let src = "<div>\n  <p>ok</p>\n  <\n</div>";
+        let cfg = StripConfig {
+            syntax_errors: SyntaxErrorPolicy::Strict,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte] {
+            let err = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap_err();
+            assert!(
+                matches!(err, StripError::SyntaxError { line: 3, .. }),
+                "unexpected error: {err:?}"
+            );
+
+            // Lenient mode still strips the file.
+            assert!(strip_whitespace_no_sourcemap(src, language, &StripConfig::default()).is_ok());
+        }
+    }
+
+    /// Rewrites a gap by re-emitting both neighbours, so its edit covers them.
+    #[derive(Debug)]
+    struct SwallowingGaps;
+
+    impl crate::GapRewriter for SwallowingGaps {
+        fn rewrite_gap(
+            &self,
+            source: &str,
+            prev: tree_sitter::Node<'_>,
+            next: tree_sitter::Node<'_>,
+            _gap: &str,
+            _language: Language,
+        ) -> Option<Edit> {
+            let replacement = format!(
+                "{}{}",
+                &source[prev.byte_range()],
+                &source[next.byte_range()]
+            );
+            Some(Edit {
+                start: prev.start_byte(),
+                end: next.end_byte(),
+                output_byte_to_input_byte: vec![None; replacement.len()],
+                replacement,
+                moved_delim_len: 0,
+            })
+        }
+    }
+
+    /// Sources whose custom gap edits (see `SwallowingGaps`) reach into a protected span, with the
+    /// kind and range of that span.
+    const PROTECTED_SPAN_CASES: [(Language, &str, &str, std::ops::Range<usize>); 3] = [
+        (
+            Language::Html,
+            "<p>a</p>\n<script>let a;</script>",
+            "script",
+            17..23,
+        ),
+        (
             Language::Svelte,
-            &StripConfig::default(),
-        )
-        .unwrap();
-        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
-        assert_eq!(sm.get_source(0), Some("input.svelte"));
+            "<style>p {}</style>\n<p>a</p>",
+            "style",
+            7..11,
+        ),
+        (
+            Language::Astro,
+            "---\nlet a;\n---\n<p>a</p>",
+            "frontmatter",
+            0..14,
+        ),
+    ];
+
+    /// By default, edits reaching into frontmatter or script/style contents fail with
+    /// `ProtectedSpanEdited` naming the span.
+    #[test]
+    fn edits_in_protected_spans_fail_by_default() {
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(std::sync::Arc::new(SwallowingGaps)),
+            ..Default::default()
+        };
+        for (language, src, kind, span) in PROTECTED_SPAN_CASES {
+            let err = strip_whitespace_no_sourcemap(src, language, &config).unwrap_err();
+            match err {
+                StripError::ProtectedSpanEdited {
+                    kind: k,
+                    span_start,
+                    span_end,
+                    ..
+                } => assert_eq!((k, span_start..span_end), (kind, span), "{src:?}"),
+                _ => panic!("unexpected error for {src:?}: {err:?}"),
+            }
+            assert_eq!(err.span(), Some(0..src.len()));
+        }
     }
 
-    /// Rotates an opener prefix (`{`) left over a gap.
+    /// In lenient mode, edits reaching into protected spans are dropped and the source is left
+    /// unchanged.
     #[test]
-    fn rotates_prefix_left_over_gap() {
-        let (out, map) = rotate_prefix_over_gap(b"{", "\n  ");
-        assert_eq!(out, "{\n  ");
-        assert_eq!(map.len(), 4);
-        assert_is_permutation(&map);
+    fn edits_in_protected_spans_are_dropped_in_lenient_mode() {
+        let config = StripConfig {
+            gap_strategy: GapStrategy::Custom(std::sync::Arc::new(SwallowingGaps)),
+            protected_spans: ProtectedSpanPolicy::Lenient,
+            ..Default::default()
+        };
+        for (language, src, _, _) in PROTECTED_SPAN_CASES {
+            let out = strip_whitespace_no_sourcemap(src, language, &config).unwrap();
+            assert_eq!(out, src, "{language:?}");
+        }
     }
 
-    /// Detects blank lines in LF and CRLF whitespace.
+    /// `SkipErrorSubtrees` leaves containers with errors alone but strips error-free ones.
     #[test]
-    fn contains_blank_line_lf_and_crlf() {
-        assert!(contains_blank_line("\n\n"));
-        assert!(contains_blank_line(" \n\n  "));
-        assert!(contains_blank_line("\r\n\r\n"));
-        assert!(!contains_blank_line("\n  \n"));
+    fn skip_error_subtrees_keeps_erroneous_containers() {
+        let src = "<section>\n  <p>\n    ok\n  </p>\n  <\n</section>";
+        let cfg = StripConfig {
+            syntax_errors: SyntaxErrorPolicy::SkipErrorSubtrees,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte] {
+            let out = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+            assert!(out.starts_with("<section>\n  <p"), "output: {out:?}");
+            assert!(out.contains(">ok</p"), "output: {out:?}");
+        }
     }
 
-    /// End-tag delimiter rotation steals indentation when available (both languages).
+    // Astro-specific tests
+
+    /// Astro JSX-like nested interpolations.
     #[test]
-    fn rewrite_rotates_gt_before_text_with_indent_steal() {
-        let src = "<span>\n  text</span>";
-        let out = strip_all(src);
-        assert_eq!(out, "<span \n >text</span>");
+    fn astro_nested_interpolations() {
+        let src = "<ul>\n  { items.map(item => (\n    <li> {item} </li>\n  )) }\n</ul>";
+        let exp = "<ul \n >{ items.map(item => (\n    <li >{item }</li>\n  )) \n}</ul>";
+        let out = strip_astro(src);
+        assert_eq!(out, exp);
     }
 
-    /// End-tag delimiter rotation without indentation (both languages).
+    // Astro JSX-like conditional rendering.
     #[test]
-    fn rewrite_rotates_gt_before_text_without_indent() {
-        let src = "<span>\ntext</span>";
-        let out = strip_all(src);
-        assert_eq!(out, "<span\n>text</span>");
+    fn astro_conditional_rendering() {
+        let src = "<div>\n  { show ?\n    <span>\n      yes\n    </span> :\n    <span>\n      no\n    </span>\n  }\n</div>";
+        let exp = "<div \n >{ show ?\n    <span \n     >yes</span\n    > :\n    <span \n     >no</span\n    >\n  \n}</div>";
+        let out = strip_astro(src);
+        assert_eq!(out, exp);
     }
 
-    /// Interpolation `}` delimiter rotation.
+    // Vue-specific tests
+
+    /// Strips whitespace using the default config for Vue.
+    fn strip_vue(src: &str) -> String {
+        strip_whitespace_no_sourcemap(src, Language::Vue, &StripConfig::default()).unwrap()
+    }
+
+    /// Vue `<template>` children and `{{ }}` interpolations are stripped; `<script>` is untouched.
     #[test]
-    fn rewrite_rotates_rbrace_before_next_node() {
-        let src = "{a}\n  <b/>";
-        let out = strip_all(src);
-        assert_eq!(out, "{a \n }<b/>");
+    fn vue_template_and_interpolation_strip_whitespace() {
+        let src = "<template>\n  <div>\n    {{ msg }}\n  </div>\n</template>\n\n<script>\nexport default {}\n</script>\n";
+        let exp = "<template \n ><div \n   >{{ msg  \n }}</div\n></template>\n\n<script>\nexport default {}\n</script>\n";
+        let out = strip_vue(src);
+        assert_eq!(out, exp);
+    }
+
+    /// The Vue grammar keeps whitespace before `{{` inside the text node, so there is no gap to
+    /// strip; before a tag, the `<tag` opener prefix is rotated left as usual.
+    #[test]
+    fn vue_text_before_interpolation_keeps_its_whitespace() {
+        let src = "<template>\n  <p>Hello\n    {{ name }}</p>\n</template>";
+        let exp = "<template \n ><p>Hello\n    {{ name }}</p\n></template>";
+        assert_eq!(strip_vue(src), exp);
+
+        let src = "<template>\n  <p>Hello\n    <b>{{ name }}</b></p>\n</template>";
+        let exp = "<template \n ><p>Hello<b\n    >{{ name }}</b></p\n></template>";
+        assert_eq!(strip_vue(src), exp);
     }
 
-    /// Comment end delimiter (`-->`) rotation does not corrupt comment bytes.
+    /// Elements with `v-for`/`v-if` directives are stripped like any other element.
     #[test]
-    fn rewrite_rotates_comment_end_before_next_node() {
-        let src = "<!--c-->\n  <span/>";
-        let out = strip_all(src);
-        assert_eq!(out, "<!--c \n --><span/>");
+    fn vue_directive_elements_strip_whitespace() {
+        let src = "<template>\n  <ul v-if=\"show\">\n    <li v-for=\"i in items\">{{ i }}</li>\n  </ul>\n</template>";
+        let exp = "<template \n ><ul v-if=\"show\" \n   ><li v-for=\"i in items\">{{ i }}</li \n ></ul\n></template>";
+        let out = strip_vue(src);
+        assert_eq!(out, exp);
     }
 
-    /// `<tagname` opener prefix rotation after text.
+    /// Vue `<style>` blocks are left untouched.
     #[test]
-    fn rewrite_rotates_tag_prefix_left_after_text() {
-        let src = "hi\n  <span/>";
-        let out = strip_all(src);
-        assert_eq!(out, "hi<span\n  />");
+    fn vue_style_block_is_untouched() {
+        let src = "<style>\n  .a {\n    color: red;\n  }\n</style>\n";
+        let out = strip_vue(src);
+        assert_eq!(out, src);
     }
 
-    /// `<!--` opener prefix rotation after text.
+    // HTML-specific tests
+
+    /// Strips whitespace using the default config for plain HTML.
+    fn strip_html(src: &str) -> String {
+        strip_whitespace_no_sourcemap(src, Language::Html, &StripConfig::default()).unwrap()
+    }
+
+    /// Full document including the doctype.
     #[test]
-    fn rewrite_rotates_comment_prefix_left_after_text() {
-        let src = "hi\n  <!--c-->";
-        let out = strip_all(src);
-        assert_eq!(out, "hi<!--\n  c-->");
+    fn html_document_strips_whitespace() {
+        let src = "<!DOCTYPE html>\n<html>\n  <body>\n    <p>hi</p>\n  </body>\n</html>\n";
+        let exp = "<!DOCTYPE html\n><html \n ><body \n   ><p>hi</p \n ></body\n></html>\n";
+        let out = strip_html(src);
+        assert_eq!(out, exp);
     }
 
-    /// `{` opener prefix rotation after text.
+    /// `<script>`/`<style>` content is raw text and left untouched; the elements themselves rotate.
     #[test]
-    fn rewrite_rotates_interpolation_prefix_left_after_text() {
-        let src = "hi\n  {a}";
-        let out = strip_all(src);
-        assert_eq!(out, "hi{\n  a}");
+    fn html_script_and_style_content_is_untouched() {
+        let src = "<div>\n  <script>\n    let a = 1;\n  </script>\n  <style>\n    p { color: red; }\n  </style>\n</div>";
+        let exp = "<div \n ><script>\n    let a = 1;\n  </script \n ><style>\n    p { color: red; }\n  </style\n></div>";
+        let out = strip_html(src);
+        assert_eq!(out, exp);
     }
 
-    /// By default, blank-line gaps are not preserved.
+    /// `<script` opener prefix rotation after text.
     #[test]
-    fn rewrite_does_not_preserve_blank_line_gaps_by_default() {
-        let src = "<a></a>\n\n<b/>";
-        let out = strip_all(src);
-        assert_ne!(out, src);
+    fn html_rotates_script_prefix_left_after_text() {
+        let src = "<p>hi\n  <script>x()</script></p>";
+        let exp = "<p>hi<script\n  >x()</script></p>";
+        let out = strip_html(src);
+        assert_eq!(out, exp);
+    }
 
-        let src_crlf = "<a></a>\r\n\r\n<b/>";
-        let out_crlf = strip_all(src_crlf);
-        assert_ne!(out_crlf, src_crlf);
+    /// Whitespace-sensitive elements are honored in HTML mode too.
+    #[test]
+    fn html_pre_is_untouched() {
+        let src = "<div>\n  <pre>\n  a\n    b\n  </pre>\n</div>";
+        let exp = "<div \n ><pre>\n  a\n    b\n  </pre\n></div>";
+        let out = strip_html(src);
+        assert_eq!(out, exp);
     }
 
-    /// With config enabled, blank-line gaps are preserved.
+    /// Text whitespace is only collapsed when requested, to a space or a line break.
     #[test]
-    fn rewrite_preserves_blank_line_gaps_with_config() {
-        let src = "<a></a>\n\n<b/>";
-        let out = strip_all_cfg(src, true);
-        assert_eq!(out, src);
+    fn text_whitespace_collapses_runs_between_words() {
+        let src = "<p>\n  Hello,\n      big \t world\n</p>";
+        let collapse = |text_whitespace| StripConfig {
+            text_whitespace,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            let out =
+                |cfg: &StripConfig| strip_whitespace_no_sourcemap(src, language, cfg).unwrap();
+            assert_eq!(
+                out(&StripConfig::default()),
+                "<p \n >Hello,\n      big \t world</p\n>"
+            );
+            assert_eq!(
+                out(&collapse(TextWhitespace::CollapseToSpace)),
+                "<p \n >Hello, big world</p\n>"
+            );
+            assert_eq!(
+                out(&collapse(TextWhitespace::CollapseToNewline)),
+                "<p \n >Hello,\nbig world</p\n>"
+            );
+        }
 
-        let src_crlf = "<a></a>\r\n\r\n<b/>";
-        let out_crlf = strip_all_cfg(src_crlf, true);
-        assert_eq!(out_crlf, src_crlf);
+        let crlf = "<p>a \r\n\r\n  b</p>";
+        let out = strip_whitespace_no_sourcemap(
+            crlf,
+            Language::Html,
+            &collapse(TextWhitespace::CollapseToNewline),
+        )
+        .unwrap();
+        assert_eq!(out, "<p>a\r\nb</p>");
     }
 
-    /// Whitespace inside `{ ... }` interpolation expressions is preserved (both languages).
+    /// Collapsed runs keep sourcemaps accurate and are reported; `<pre>` text is left alone.
     #[test]
-    fn rewrite_does_not_touch_whitespace_inside_interpolation_expression() {
-        let src = "{ a +  b }";
-        let out = strip_all(src);
-        assert_eq!(out, src);
+    fn text_whitespace_collapse_maps_and_reports_runs() {
+        let src = "<div>\n  Hello,\n      big world\n  <pre>a    b</pre>\n</div>";
+        let cfg = StripConfig {
+            text_whitespace: TextWhitespace::CollapseToSpace,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(
+            res.code,
+            "<div \n >Hello, big world<pre\n  >a    b</pre\n></div>"
+        );
+        assert_eq!(res.report.collapsed_text_runs, 1);
+        assert_eq!(res.report.prefix_rotations, 1);
+
+        // "big" starts a new mapping back to its original line and column.
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        let token = sm.lookup_token(1, 9).unwrap();
+        assert_eq!((token.get_src_line(), token.get_src_col()), (2, 6));
     }
 
-    /// Delimiter rotation when an end tag is followed by a start tag.
+    /// Whitespace between text and block-level boundaries is deleted; inline contexts rotate.
     #[test]
-    fn end_tag_then_start_tag() {
-        let src = "<a>x</a>\n  <b></b>";
-        let out = strip_all(src);
-        assert_eq!(out, "<a>x</a \n ><b></b>");
+    fn trim_block_text_deletes_whitespace_at_block_boundaries() {
+        let cfg = StripConfig {
+            trim_block_text: true,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            let out = |src| strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+            assert_eq!(out("<p>\n  Hello\n</p>"), "<p>Hello</p>");
+            assert_eq!(
+                out("<div>\n  intro\n  <p>x</p>\n  outro\n</div>"),
+                "<div>intro<p>x</p>outro</div>"
+            );
+            assert_eq!(out("<span>\n  Hello\n</span>"), "<span \n >Hello</span\n>");
+            assert_eq!(out("<p>\n  a\n  <b>x</b>\n</p>"), "<p>a<b\n  >x</b\n></p>");
+        }
+
+        // Blank lines are still preserved on request.
+        let cfg = StripConfig {
+            preserve_blank_lines: true,
+            ..cfg
+        };
+        let src = "<p>\n\n  Hello\n</p>";
+        let (out, report) = strip_whitespace_with_report(src, Language::Html, &cfg).unwrap();
+        assert_eq!(out, "<p>\n\n  Hello</p>");
+        assert_eq!(report.trimmed_block_text, 1);
+        assert_eq!(report.skipped_blank_lines, 1);
     }
 
-    /// Delimiter rotation when an end tag is followed by a self-closing tag.
+    /// Components named after block-level elements are inline unless listed as block-level.
     #[test]
-    fn end_tag_then_self_closing_tag() {
-        let src = "<a>x</a>\n  <b/>";
-        let out = strip_all(src);
-        assert_eq!(out, "<a>x</a \n ><b/>");
+    fn trim_block_text_ignores_capitalized_components() {
+        let cfg = StripConfig {
+            trim_block_text: true,
+            ..Default::default()
+        };
+        for src in [
+            "<Section>\n  Hello\n</Section>",
+            "<div>\n  <Header />\n  intro\n  <Nav>x</Nav>\n</div>",
+        ] {
+            for language in [Language::Astro, Language::Svelte] {
+                let out = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+                let default = strip_whitespace_no_sourcemap(src, language, &StripConfig::default());
+                assert_eq!(out, default.unwrap(), "{language:?}: {src:?}");
+            }
+        }
+
+        let out =
+            strip_whitespace_no_sourcemap("<SECTION>\n  Hello\n</SECTION>", Language::Html, &cfg);
+        assert_eq!(out.unwrap(), "<SECTION>Hello</SECTION>");
     }
 
-    /// Delimiter rotation when an end tag is followed by interpolation.
+    /// Deleted whitespace keeps the sourcemap pointing at the original text.
     #[test]
-    fn end_tag_then_start_interpolation() {
-        let src = "<a>x</a>\n  {b}";
-        let out = strip_all(src);
-        assert_eq!(out, "<a>x</a \n >{b}");
+    fn trim_block_text_maps_trimmed_text() {
+        let src = "<div>\n  <p>\n    Hello\n  </p>\n</div>";
+        let cfg = StripConfig {
+            trim_block_text: true,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(res.code, "<div \n ><p>Hello</p\n></div>");
+
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        let token = sm.lookup_token(1, 5).unwrap();
+        assert_eq!((token.get_src_line(), token.get_src_col()), (2, 4));
     }
 
-    /// Delimiter rotation when an end tag is followed by text.
+    /// Gaps between inline neighbours keep a single space; gaps next to blocks still rotate.
     #[test]
-    fn end_tag_then_text() {
-        let src = "<a>x</a>\n  text";
-        let out = strip_all(src);
-        assert_eq!(out, "<a>x</a \n >text");
+    fn inline_safe_keeps_a_space_between_inline_neighbours() {
+        let cfg = StripConfig {
+            inline_safe: true,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            let out = |src| strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+            assert_eq!(out("<a>One</a>\n<a>Two</a>"), "<a>One</a> <a>Two</a>");
+            assert_eq!(
+                out("<div>\n  <a>One</a>\n  <a>Two</a>\n</div>"),
+                "<div \n ><a>One</a> <a>Two</a\n></div>"
+            );
+            assert_eq!(
+                out("<span>\n  Hello\n  <b>x</b> y\n</span>"),
+                "<span> Hello <b>x</b> y </span>"
+            );
+            assert_eq!(out("<p>a</p>\n<b>x</b>"), "<p>a</p\n><b>x</b>");
+        }
+
+        let (out, report) = strip_whitespace_with_report(
+            "<div>\n  <Card/>\n  <Card/>\n  text\n</div>",
+            Language::Astro,
+            &StripConfig {
+                block_level_components: vec!["Card".to_string()],
+                ..cfg.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(out, "<div \n ><Card  \n/><Card  \n/>text</div\n>");
+        assert_eq!(report.inline_gaps, 0);
+
+        // Components named after block-level elements are inline unless listed.
+        for language in [Language::Astro, Language::Svelte] {
+            let out = strip_whitespace_no_sourcemap("<Header />\n<Nav />", language, &cfg);
+            assert_eq!(out.unwrap(), "<Header /> <Nav />", "{language:?}");
+        }
+
+        // Nodes that render nothing in place are never inline neighbours.
+        let src =
+            "<!DOCTYPE html>\n<a>x</a>\n<script>a()</script>\n<b>y</b>\n<title>t</title>\n<i>z</i>";
+        let out = strip_whitespace_no_sourcemap(src, Language::Html, &cfg).unwrap();
+        assert_eq!(
+            out,
+            "<!DOCTYPE html\n><a>x</a\n><script>a()</script\n><b>y</b\n><title>t</title\n><i>z</i>"
+        );
+
+        let (_, report) =
+            strip_whitespace_with_report("<a>One</a>\n<a>Two</a>", Language::Html, &cfg).unwrap();
+        assert_eq!((report.inline_gaps, report.collapsed_text_runs), (1, 0));
     }
 
-    /// `/>` delimiter rotation when followed by a start tag.
+    /// `data-strip-whitespace="off"` protects a subtree; `"on"` re-enables a nested one.
     #[test]
-    fn self_closing_tag_then_start_tag() {
-        let src = "<a/>\n  <b>y</b>";
-        let out = strip_all(src);
-        assert_eq!(out, "<a  \n/><b>y</b>");
+    fn marker_attribute_switches_stripping_per_subtree() {
+        let src = "<div>\n  <section data-strip-whitespace=\"off\">\n    <p>\n      a\n    </p>\n    <ul data-strip-whitespace=\"on\">\n      <li>b</li>\n    </ul>\n  </section>\n  <p>\n    c\n  </p>\n</div>";
+        let exp = "<div \n ><section data-strip-whitespace=\"off\">\n    <p>\n      a\n    </p>\n    <ul data-strip-whitespace=\"on\" \n     ><li>b</li \n   ></ul>\n  </section \n ><p \n   >c</p\n  \n></div>";
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            let out = strip_whitespace_no_sourcemap(src, language, &StripConfig::default());
+            assert_eq!(out.unwrap(), exp, "{language:?}");
+        }
     }
 
-    /// `/>` delimiter rotation when followed by another self-closing tag.
+    /// Markers can be removed from the output; the sourcemap skips over them.
     #[test]
-    fn self_closing_tag_then_self_closing_tag() {
-        let src = "<a/>\n  <b/>";
-        let out = strip_all(src);
-        assert_eq!(out, "<a  \n/><b/>");
+    fn marker_attributes_can_be_removed() {
+        let src = "<div data-strip-whitespace=\"off\" class=\"x\">\n  <p>a</p>\n</div>\n<p\n  data-strip-whitespace='on'>\n  b\n</p>";
+        let cfg = StripConfig {
+            remove_marker_attributes: true,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(
+            res.code,
+            "<div class=\"x\">\n  <p>a</p>\n</div\n><p \n >b</p\n>"
+        );
+        assert_eq!(res.report.removed_marker_attributes, 2);
+
+        // The space before `class` still maps to its original column.
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        let token = sm.lookup_token(0, 4).unwrap();
+        assert_eq!(
+            (
+                token.get_dst_col(),
+                token.get_src_line(),
+                token.get_src_col()
+            ),
+            (4, 0, 32)
+        );
+
+        // The removed bytes count as saved.
+        let src = "<p data-strip-whitespace=\"on\">a</p>";
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(res.code, "<p>a</p>");
+        assert_eq!(res.report.estimated_bytes_saved, src.len() - res.code.len());
     }
 
-    /// `/>` delimiter rotation when followed by interpolation.
+    /// Whitespace between attributes collapses to a space, optionally keeping line starts.
     #[test]
-    fn self_closing_tag_then_start_interpolation() {
-        let src = "<a/>\n  {b}";
-        let out = strip_all(src);
-        assert_eq!(out, "<a  \n/>{b}");
+    fn attribute_whitespace_collapses_runs_between_attributes() {
+        let src =
+            "<div\n    class=\"a\"   id=\"b\"\n  >\n  <img\tsrc=\"x\"\r\n    alt=\"\" />\n</div>";
+        for (mode, exp) in [
+            (
+                AttributeWhitespace::Preserve,
+                "<div\n    class=\"a\"   id=\"b\"\n   \n ><img\tsrc=\"x\"\r\n    alt=\"\" \n/></div>",
+            ),
+            (
+                AttributeWhitespace::CollapseToSpace,
+                "<div class=\"a\" id=\"b\"\n   \n ><img src=\"x\" alt=\"\" \n/></div>",
+            ),
+            (
+                AttributeWhitespace::KeepLineBreaks,
+                "<div\n    class=\"a\" id=\"b\"\n   \n ><img src=\"x\"\r\n    alt=\"\" \n/></div>",
+            ),
+        ] {
+            let cfg = StripConfig {
+                attribute_whitespace: mode,
+                ..Default::default()
+            };
+            for language in [Language::Astro, Language::Svelte, Language::Html] {
+                let out = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+                assert_eq!(out, exp, "{mode:?} {language:?}");
+            }
+        }
     }
 
-    /// `/>` delimiter rotation when followed by text.
+    /// Collapsed attribute whitespace maps back to its source and is reported.
     #[test]
-    fn self_closing_tag_then_text() {
-        let src = "<a/>\n  text";
-        let out = strip_all(src);
-        assert_eq!(out, "<a  \n/>text");
+    fn attribute_whitespace_maps_and_reports_runs() {
+        let src = "<p>\n  <a\n    href=\"/\"\n    data-strip-whitespace=\"on\"\n    title=\"t\">x</a>\n</p>";
+        let cfg = StripConfig {
+            attribute_whitespace: AttributeWhitespace::CollapseToSpace,
+            remove_marker_attributes: true,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(res.code, "<p \n ><a href=\"/\" title=\"t\">x</a\n></p>");
+        assert_eq!(res.report.collapsed_attribute_gaps, 2);
+        assert_eq!(res.report.removed_marker_attributes, 1);
+        assert_eq!(res.report.collapsed_text_runs, 0);
+
+        // `title` moved up from line 4 and still maps there.
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        let line_start = res.code.find('\n').unwrap() + 1;
+        let col = (res.code.find("title").unwrap() - line_start) as u32;
+        let token = sm.lookup_token(1, col).unwrap();
+        assert_eq!((token.get_src_line(), token.get_src_col()), (4, 4));
     }
 
-    /// `}` delimiter rotation when interpolation is followed by a start tag.
+    /// Removed comments merge the whitespace around them into one gap.
     #[test]
-    fn end_interpolation_then_start_tag() {
-        let src = "{a}\n  <b>y</b>";
-        let out = strip_all(src);
-        assert_eq!(out, "{a \n }<b>y</b>");
+    fn remove_comments_strips_the_merged_gap() {
+        let src = "<div>\n  <!-- a -->\n  <p>x</p>\n  <!-- b -->\n  <!-- c -->\n  text\n  <!-- d --><br/>\n</div>\n<!-- e -->";
+        let cfg = StripConfig {
+            remove_comments: true,
+            ..Default::default()
+        };
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            let (out, report) = strip_whitespace_with_report(src, language, &cfg).unwrap();
+            assert_eq!(
+                out, "<div \n ><p>x</p \n >text<br\n/></div>",
+                "{language:?}"
+            );
+            assert_eq!(report.removed_comments, 5);
+        }
     }
 
-    /// `}` delimiter rotation when interpolation is followed by a self-closing tag.
+    /// Directives, conditional, license and `svelte-ignore` comments are kept.
     #[test]
-    fn end_interpolation_then_self_closing_tag() {
-        let src = "{a}\n  <b/>";
-        let out = strip_all(src);
-        assert_eq!(out, "{a \n }<b/>");
+    fn remove_comments_keeps_special_comments() {
+        let src = "<div>\n  <!--! MIT -->\n  <!--[if IE]><p>ie</p><![endif]-->\n  <!-- svelte-ignore a11y -->\n  <!-- strip-whitespace-ignore -->\n  <p>\n    a\n  </p>\n</div>";
+        let cfg = StripConfig {
+            remove_comments: true,
+            ..Default::default()
+        };
+        let (out, report) = strip_whitespace_with_report(src, Language::Svelte, &cfg).unwrap();
+        assert_eq!(
+            out,
+            "<div \n ><!--! MIT  \n --><!--[if IE]><p>ie</p><![endif] \n --><!-- svelte-ignore a11y  \n --><!-- strip-whitespace-ignore  \n --><p>\n    a\n  </p>\n</div>"
+        );
+        assert_eq!(report.removed_comments, 0);
     }
 
-    /// `}` delimiter rotation when interpolation is followed by interpolation.
+    /// Gaps that are left alone still lose their comments; the sourcemap skips over them.
     #[test]
-    fn end_interpolation_then_start_interpolation() {
-        let src = "{a}\n  {b}";
-        let out = strip_all(src);
-        assert_eq!(out, "{a \n }{b}");
+    fn remove_comments_in_preserved_gaps() {
+        let src =
+            "<p>a</p>\n\n<!-- gone -->\n<p>b</p>\n<span>c</span>\n<!-- gone --> <span>d</span>";
+        let cfg = StripConfig {
+            remove_comments: true,
+            preserve_blank_lines: true,
+            inline_safe: true,
+            ..Default::default()
+        };
+        let res = strip_whitespace(src, "input.html", Language::Html, &cfg).unwrap();
+        assert_eq!(
+            res.code,
+            "<p>a</p>\n\n<p>b</p\n><span>c</span> <span>d</span>"
+        );
+        assert_eq!(res.report.removed_comments, 2);
+
+        let sm = sourcemap::SourceMap::from_slice(res.map.as_bytes()).unwrap();
+        // `<p>b` keeps its line relative to the blank line above it.
+        let token = sm.lookup_token(2, 0).unwrap();
+        assert_eq!((token.get_src_line(), token.get_src_col()), (3, 0));
     }
 
-    /// `}` delimiter rotation when interpolation is followed by text.
+    /// Tag rules match tag names with `*` wildcards, case-insensitively only in HTML.
     #[test]
-    fn end_interpolation_then_text() {
-        let src = "{a}\n  text";
-        let out = strip_all(src);
-        assert_eq!(out, "{a \n }text");
+    fn tag_rule_patterns_match_tag_names() {
+        let rule = |pattern: &str| TagRule::new(pattern, TagAction::Preserve);
+        for language in [
+            Language::Astro,
+            Language::Svelte,
+            Language::Vue,
+            Language::Html,
+        ] {
+            let matches = |pattern, tag_name| rule(pattern).matches(tag_name, language);
+            assert!(matches("Trans", "Trans"));
+            assert!(!matches("Trans", "Transition"));
+            assert!(matches("Icon*", "IconArrow"));
+            assert!(matches("Icon*", "Icon"));
+            assert!(matches("*.Code", "Docs.Code"));
+            assert!(matches("a*b*c", "aXbYbc"));
+            assert!(!matches("a*b*c", "aXbYb"));
+            assert!(matches("*", "anything"));
+            assert_eq!(matches("Trans", "trans"), language == Language::Html);
+            assert_eq!(matches("Icon*", "iconArrow"), language == Language::Html);
+        }
     }
 
-    /// Opener prefix rotation when text is followed by a start tag.
+    /// Each tag action controls the element's contents and the gaps around it.
     #[test]
-    fn text_then_start_tag() {
-        let src = "hi\n  <b>y</b>";
-        let out = strip_all(src);
-        assert_eq!(out, "hi<b\n  >y</b>");
+    fn tag_rules_control_contents_and_surrounding_gaps() {
+        let src = "<div>\n  <Code>\n    <b>x</b>\n  </Code>\n  <p>y</p>\n</div>";
+        for (action, exp) in [
+            (
+                TagAction::Strip,
+                "<div \n ><Code \n   ><b>x</b \n ></Code \n ><p>y</p\n></div>",
+            ),
+            (
+                TagAction::Preserve,
+                "<div>\n  <Code>\n    <b>x</b>\n  </Code>\n  <p>y</p\n></div>",
+            ),
+            (
+                TagAction::StripChildren,
+                "<div>\n  <Code \n   ><b>x</b \n ></Code>\n  <p>y</p\n></div>",
+            ),
+            (
+                TagAction::StripOutside,
+                "<div \n ><Code>\n    <b>x</b>\n  </Code \n ><p>y</p\n></div>",
+            ),
+            (
+                TagAction::CollapseToSpace,
+                "<div \n ><Code> <b>x</b> </Code \n ><p>y</p\n></div>",
+            ),
+        ] {
+            let cfg = StripConfig {
+                tag_rules: vec![TagRule::new("Code", action)],
+                ..Default::default()
+            };
+            for language in [Language::Astro, Language::Svelte, Language::Html] {
+                let out = strip_whitespace_no_sourcemap(src, language, &cfg).unwrap();
+                assert_eq!(out, exp, "{action:?} {language:?}");
+            }
+        }
     }
 
-    /// Opener prefix rotation when text is followed by a self-closing tag.
+    /// Rules override built-in whitespace-sensitive elements; markers override rules.
     #[test]
-    fn text_then_self_closing_tag() {
-        let src = "hi\n  <b/>";
-        let out = strip_all(src);
-        assert_eq!(out, "hi<b\n  />");
+    fn tag_rules_override_defaults_and_yield_to_markers() {
+        let src = "<div>\n  <pre>\n    <b>x</b>\n  </pre>\n  <Trans data-strip-whitespace=\"off\">\n    <b>y</b>\n  </Trans>\n</div>";
+        let cfg = StripConfig {
+            tag_rules: vec![
+                TagRule::new("pre", TagAction::Strip),
+                TagRule::new("Trans", TagAction::CollapseToSpace),
+            ],
+            ..Default::default()
+        };
+        let out = strip_whitespace_no_sourcemap(src, Language::Html, &cfg).unwrap();
+        assert_eq!(
+            out,
+            "<div \n ><pre \n   ><b>x</b \n ></pre \n ><Trans data-strip-whitespace=\"off\">\n    <b>y</b>\n  </Trans\n></div>"
+        );
     }
 
-    /// Whitespace between adjacent text nodes is never modified.
+    /// `<svelte:options preserveWhitespace />` leaves the whole Svelte file untouched.
     #[test]
-    fn text_then_text_do_not_strip() {
-        // Even if tree-sitter ever produces adjacent text nodes, we must not
-        // strip whitespace between them. This is a simple no-op sanity check.
-        let src = "hi\n  there";
-        let out = strip_all(src);
-        assert_eq!(out, src);
-    }
+    fn svelte_preserve_whitespace_option_disables_stripping() {
+        let body = "<div>\n  <p>x</p>\n</div>";
+        for options in [
+            "<svelte:options preserveWhitespace={true} />",
+            "<svelte:options preserveWhitespace />",
+            "<svelte:options runes preserveWhitespace=\"true\" />",
+        ] {
+            let src = format!("{options}\n{body}");
+            let (out, report) =
+                strip_whitespace_with_report(&src, Language::Svelte, &StripConfig::default())
+                    .unwrap();
+            assert_eq!(out, src);
+            assert!(report.suppressions.svelte_preserve_whitespace);
+        }
 
-    // Astro-specific tests
+        let src = format!("<svelte:options preserveWhitespace={{false}} />\n{body}");
+        let (out, report) =
+            strip_whitespace_with_report(&src, Language::Svelte, &StripConfig::default()).unwrap();
+        assert_ne!(out, src);
+        assert!(!report.suppressions.any());
 
-    /// Astro JSX-like nested interpolations.
-    #[test]
-    fn astro_nested_interpolations() {
-        let src = "<ul>\n  { items.map(item => (\n    <li> {item} </li>\n  )) }\n</ul>";
-        let exp = "<ul \n >{ items.map(item => (\n    <li >{item }</li>\n  )) \n}</ul>";
-        let out = strip_astro(src);
-        assert_eq!(out, exp);
+        // Only Svelte honours the option.
+        let src = format!("<svelte:options preserveWhitespace />\n{body}");
+        assert_ne!(strip_astro(&src), src);
     }
 
-    // Astro JSX-like conditional rendering.
+    /// Astro `is:raw` elements are skipped like `<pre>`, and counted in the report.
     #[test]
-    fn astro_conditional_rendering() {
-        let src = "<div>\n  { show ?\n    <span>\n      yes\n    </span> :\n    <span>\n      no\n    </span>\n  }\n</div>";
-        let exp = "<div \n >{ show ?\n    <span \n     >yes</span\n    > :\n    <span \n     >no</span\n    >\n  \n}</div>";
-        let out = strip_astro(src);
-        assert_eq!(out, exp);
+    fn astro_is_raw_elements_are_untouched() {
+        let src = "<div>\n  <code is:raw>\n    {x}\n  </code>\n  <p>\n    y\n  </p>\n</div>";
+        let (out, report) =
+            strip_whitespace_with_report(src, Language::Astro, &StripConfig::default()).unwrap();
+        assert_eq!(
+            out,
+            "<div \n ><code is:raw>\n    {x}\n  </code \n ><p \n   >y</p\n  \n></div>"
+        );
+        assert_eq!(report.suppressions.astro_raw_elements, 1);
+        assert!(!report.suppressions.svelte_preserve_whitespace);
+
+        let (_, report) =
+            strip_whitespace_with_report(src, Language::Svelte, &StripConfig::default()).unwrap();
+        assert!(!report.suppressions.any());
     }
 
     // Svelte-specific tests
@@ -1034,4 +3314,35 @@ mod tests {
         let out = strip_svelte(src);
         assert_eq!(out, exp);
     }
+
+    /// Svelte `{#snippet}` bodies are stripped like other blocks.
+    #[test]
+    fn svelte_snippet_block_strips_whitespace() {
+        let src =
+            "{#snippet row(item)}\n  <td>{item}</td>\n  <td>x</td>\n{/snippet}\n<table></table>";
+        let exp =
+            "{#snippet row(item) \n }<td>{item}</td \n ><td>x</td\n>{/snippet\n}<table></table>";
+        let out = strip_svelte(src);
+        assert_eq!(out, exp);
+    }
+
+    /// Svelte `{@render}`, `{@html}`, `{@const}` and `{@debug}` tags rotate their braces.
+    #[test]
+    fn svelte_special_tags_strip_whitespace() {
+        let src = "{#each xs as x}\n  {@const a = x * 2}\n  {@debug a}\n  {@html a}\n  {@render b(a)}\n{/each}";
+        let exp = "{#each xs as x \n }{@const a = x * 2 \n }{@debug a \n }{@html a \n }{@render b(a)\n}{/each}";
+        let out = strip_svelte(src);
+        assert_eq!(out, exp);
+
+        // After text, the opening brace moves left together with its keyword, as block tags do.
+        let src = "<p>\n  By\n  {@render author()}\n</p>";
+        let exp = "<p \n >By{@render\n   author()\n}</p>";
+        let out = strip_svelte(src);
+        assert_eq!(out, exp);
+
+        let src = "<p>\n  By\n  {#if a}{a}{/if}\n</p>";
+        let exp = "<p \n >By{#if\n   a}{a}{/if\n}</p>";
+        let out = strip_svelte(src);
+        assert_eq!(out, exp);
+    }
 }
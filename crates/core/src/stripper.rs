@@ -0,0 +1,262 @@
+//! A reusable stripper that owns its parsers and configuration.
+//!
+//! The free functions in [`strip`](crate::strip) share thread-local parsers, which is convenient
+//! but leaves the host no control over parser lifetime or settings. A [`Stripper`] owns its
+//! Tree-sitter parsers instead, so a worker pool can give each worker its own instance and
+//! several configurations can run side by side.
+
+use std::sync::{Arc, atomic::AtomicBool};
+
+use crate::{
+    Language, StripError,
+    edit::{Edit, create_sourcemap, rewrite_sourcemap},
+    incremental::{self, StripSession, TextChange},
+    parse::Parsers,
+    report::StripReport,
+    strip::{CodeAndSourcemap, StripConfig, plan, rewrite},
+};
+
+/// Whitespace stripper owning its Tree-sitter parsers and [`StripConfig`].
+///
+/// Parsers are created lazily, once per language, and reused across calls.
+#[derive(Default)]
+pub struct Stripper {
+    config: StripConfig,
+    parsers: Parsers,
+}
+
+impl Stripper {
+    /// Creates a stripper using `config`.
+    pub fn new(config: StripConfig) -> Self {
+        Self {
+            config,
+            parsers: Parsers::new(),
+        }
+    }
+
+    /// Returns the configuration used by this stripper.
+    pub fn config(&self) -> &StripConfig {
+        &self.config
+    }
+
+    /// Returns a mutable reference to the configuration, for changing it between calls.
+    pub fn config_mut(&mut self) -> &mut StripConfig {
+        &mut self.config
+    }
+
+    /// Sets a flag that, once set to `true` (e.g. from another thread), makes the current and
+    /// later parses fail with [`StripError::Cancelled`]. The stripper never resets the flag.
+    pub fn set_cancellation_flag(&mut self, flag: Option<Arc<AtomicBool>>) {
+        self.parsers.set_cancellation_flag(flag);
+    }
+
+    /// Returns the Tree-sitter parser used for `language`, e.g. to install a logger.
+    ///
+    /// The parser's language must not be changed.
+    pub fn parser_mut(&mut self, language: Language) -> &mut tree_sitter::Parser {
+        self.parsers.get_mut(language)
+    }
+
+    /// Strip inter-node whitespace and create a brand-new sourcemap.
+    ///
+    /// See [`strip_whitespace`](crate::strip_whitespace).
+    pub fn strip(
+        &mut self,
+        source: &str,
+        source_filename: &str,
+        language: Language,
+    ) -> Result<CodeAndSourcemap, StripError> {
+        let (code, edits, report) = rewrite(&mut self.parsers, source, language, &self.config)?;
+        let map = create_sourcemap(source, &code, source_filename, &edits)?;
+        Ok(CodeAndSourcemap { code, map, report })
+    }
+
+    /// Strip inter-node whitespace and rewrite an existing input sourcemap.
+    ///
+    /// See [`strip_whitespace_with_input_map`](crate::strip_whitespace_with_input_map).
+    pub fn strip_with_input_map(
+        &mut self,
+        source: &str,
+        input_map_json: &str,
+        language: Language,
+    ) -> Result<CodeAndSourcemap, StripError> {
+        let (code, edits, report) = rewrite(&mut self.parsers, source, language, &self.config)?;
+        let map = rewrite_sourcemap(source, &code, input_map_json, &edits)?;
+        Ok(CodeAndSourcemap { code, map, report })
+    }
+
+    /// Strip inter-node whitespace without producing a sourcemap.
+    pub fn strip_no_sourcemap(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<String, StripError> {
+        let (code, _, _) = rewrite(&mut self.parsers, source, language, &self.config)?;
+        Ok(code)
+    }
+
+    /// Strip inter-node whitespace without producing a sourcemap, and report what was done.
+    pub fn strip_with_report(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<(String, StripReport), StripError> {
+        let (code, _, report) = rewrite(&mut self.parsers, source, language, &self.config)?;
+        Ok((code, report))
+    }
+
+    /// Compute the whitespace edits for `source` without applying them.
+    ///
+    /// See [`plan_edits`](crate::plan_edits).
+    pub fn plan_edits(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<Vec<Edit>, StripError> {
+        plan(
+            &mut self.parsers,
+            source,
+            language,
+            &self.config,
+            &mut StripReport::default(),
+        )
+    }
+
+    /// Strip `source` in full and return a session for incremental updates.
+    ///
+    /// Read the result with [`StripSession::code`] and [`StripSession::sourcemap`].
+    pub fn start_session(
+        &mut self,
+        source: &str,
+        language: Language,
+    ) -> Result<StripSession, StripError> {
+        incremental::start(&mut self.parsers, source, language, &self.config)
+    }
+
+    /// Apply `changes` (in order, each relative to the text after the previous one) to the
+    /// session and recompute only the edits of the containers they touch.
+    ///
+    /// The resulting edits are identical to a full run over the new source. On error the session
+    /// is left unchanged.
+    pub fn update_session(
+        &mut self,
+        session: &mut StripSession,
+        changes: &[TextChange],
+    ) -> Result<(), StripError> {
+        incremental::update(&mut self.parsers, session, changes, &self.config)
+    }
+
+    /// Like [`Stripper::update_session`], for hosts that only have the new source: the change is
+    /// derived from the common prefix and suffix of the old and new sources.
+    pub fn update_session_source(
+        &mut self,
+        session: &mut StripSession,
+        new_source: &str,
+    ) -> Result<(), StripError> {
+        let changes: Vec<TextChange> = incremental::diff_sources(session.source(), new_source)
+            .into_iter()
+            .collect();
+        self.update_session(session, &changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::atomic::Ordering, time::Duration};
+
+    use super::*;
+    use crate::strip::{strip_whitespace, strip_whitespace_no_sourcemap};
+
+    /// A stripper produces the same output as the free functions, across repeated calls.
+    #[test]
+    fn stripper_matches_free_functions() {
+        let src = "<div>\n  <span>ok</span>\n  text\n</div>\n";
+        let mut stripper = Stripper::default();
+        for language in [Language::Astro, Language::Svelte, Language::Html] {
+            for _ in 0..2 {
+                let out = stripper.strip(src, "input", language).unwrap();
+                let expected =
+                    strip_whitespace(src, "input", language, &StripConfig::default()).unwrap();
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    /// Strippers with different configurations can be used side by side.
+    #[test]
+    fn strippers_with_different_configs_run_side_by_side() {
+        let src = "<a></a>\n\n<b/>";
+        let mut stripping = Stripper::default();
+        let mut preserving = Stripper::new(StripConfig {
+            preserve_blank_lines: true,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            preserving.strip_no_sourcemap(src, Language::Astro).unwrap(),
+            src
+        );
+        assert_eq!(
+            stripping.strip_no_sourcemap(src, Language::Astro).unwrap(),
+            strip_whitespace_no_sourcemap(src, Language::Astro, &StripConfig::default()).unwrap()
+        );
+
+        preserving.config_mut().preserve_blank_lines = false;
+        assert_eq!(
+            preserving.strip_no_sourcemap(src, Language::Astro).unwrap(),
+            stripping.strip_no_sourcemap(src, Language::Astro).unwrap()
+        );
+    }
+
+    /// Strippers can be moved into worker threads.
+    #[test]
+    fn stripper_is_send() {
+        let mut stripper = Stripper::default();
+        let out = std::thread::spawn(move || {
+            stripper
+                .strip_no_sourcemap("<p>\n  hi\n</p>", Language::Astro)
+                .unwrap()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(out, "<p \n >hi</p\n>");
+    }
+
+    /// A set cancellation flag fails parsing; clearing it makes the stripper usable again.
+    #[test]
+    fn cancellation_flag_cancels_parsing() {
+        let flag = Arc::new(AtomicBool::new(true));
+        let mut stripper = Stripper::default();
+        stripper.set_cancellation_flag(Some(flag.clone()));
+        let err = stripper
+            .strip_no_sourcemap("<p>\n  hi\n</p>", Language::Astro)
+            .unwrap_err();
+        assert!(matches!(err, StripError::Cancelled));
+
+        flag.store(false, Ordering::Relaxed);
+        let out = stripper
+            .strip_no_sourcemap("<p>\n  hi\n</p>", Language::Astro)
+            .unwrap();
+        assert_eq!(out, "<p \n >hi</p\n>");
+    }
+
+    /// Parses exceeding the time budget fail, and the next parse starts from scratch.
+    #[test]
+    fn parse_timeout_fails_slow_parses() {
+        let src = "<div>\n  <p>x</p>\n</div>\n".repeat(20_000);
+        let mut stripper = Stripper::new(StripConfig {
+            parse_timeout: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        let err = stripper
+            .strip_no_sourcemap(&src, Language::Html)
+            .unwrap_err();
+        assert!(matches!(err, StripError::Timeout));
+
+        stripper.config_mut().parse_timeout = Some(Duration::from_secs(60));
+        let out = stripper
+            .strip_no_sourcemap("<p>\n  hi\n</p>", Language::Html)
+            .unwrap();
+        assert_eq!(out, "<p \n >hi</p\n>");
+    }
+}
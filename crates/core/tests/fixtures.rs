@@ -26,6 +26,10 @@ fn detect_language(filename: &str) -> Language {
         Language::Astro
     } else if filename.ends_with(".svelte") {
         Language::Svelte
+    } else if filename.ends_with(".vue") {
+        Language::Vue
+    } else if filename.ends_with(".html") {
+        Language::Html
     } else {
         panic!("unknown file extension: {}", filename)
     }
@@ -44,6 +48,13 @@ fn fixtures_match_expected_output() {
         ("components.svelte", "components.out.svelte"),
         ("whitespace.svelte", "whitespace.out.svelte"),
         ("unicode.svelte", "unicode.out.svelte"),
+        ("snippet.svelte", "snippet.out.svelte"),
+        ("render.svelte", "render.out.svelte"),
+        ("html-tag.svelte", "html-tag.out.svelte"),
+        ("const-tag.svelte", "const-tag.out.svelte"),
+        ("debug-tag.svelte", "debug-tag.out.svelte"),
+        ("simple.vue", "simple.out.vue"),
+        ("simple.html", "simple.out.html"),
     ];
 
     for (input_name, expected_name) in cases {
@@ -75,6 +86,13 @@ fn fixtures_emit_parseable_sourcemaps() {
         ("components.svelte", "components.out.svelte"),
         ("whitespace.svelte", "whitespace.out.svelte"),
         ("unicode.svelte", "unicode.out.svelte"),
+        ("snippet.svelte", "snippet.out.svelte"),
+        ("render.svelte", "render.out.svelte"),
+        ("html-tag.svelte", "html-tag.out.svelte"),
+        ("const-tag.svelte", "const-tag.out.svelte"),
+        ("debug-tag.svelte", "debug-tag.out.svelte"),
+        ("simple.vue", "simple.out.vue"),
+        ("simple.html", "simple.out.html"),
     ];
 
     for (input_name, expected_name) in cases {
@@ -0,0 +1,113 @@
+use strip_whitespace::{
+    Language, StripConfig, Stripper, TagAction, TagRule, TextChange, strip_whitespace_no_sourcemap,
+};
+
+/// A config that keeps blank lines and spaces between inline neighbours.
+fn keeping_config() -> StripConfig {
+    StripConfig {
+        preserve_blank_lines: true,
+        inline_safe: true,
+        ..Default::default()
+    }
+}
+
+const HEAD: &str = "<svelte:head>\n  <title>Home</title>\n\n  <meta name=\"x\" content=\"y\" />\n  <link rel=\"icon\" href=\"/favicon.png\" />\n</svelte:head>";
+
+/// Whitespace inside `<svelte:head>` never renders, so it is stripped even where the config
+/// keeps blank lines and inline spaces elsewhere.
+#[test]
+fn svelte_head_contents_are_always_stripped() {
+    let src = format!("{HEAD}\n\n<p>\n  <b>a</b>\n\n  <i>b</i>\n</p>");
+    let out = strip_whitespace_no_sourcemap(&src, Language::Svelte, &keeping_config()).unwrap();
+    assert_eq!(
+        out,
+        "<svelte:head \n ><title>Home</title \n\n ><meta name=\"x\" content=\"y\"   \n/><link rel=\"icon\" href=\"/favicon.png\" \n/></svelte:head>\n\n<p \n ><b>a</b>\n\n  <i>b</i\n></p>"
+    );
+
+    // With the default config, the contents are stripped as usual.
+    let out = strip_whitespace_no_sourcemap(HEAD, Language::Svelte, &StripConfig::default());
+    assert_eq!(
+        out.unwrap(),
+        "<svelte:head \n ><title>Home</title \n\n ><meta name=\"x\" content=\"y\"   \n/><link rel=\"icon\" href=\"/favicon.png\" \n/></svelte:head>"
+    );
+
+    // Outside Svelte, `svelte:head` is just another tag name.
+    let out = strip_whitespace_no_sourcemap(HEAD, Language::Html, &keeping_config()).unwrap();
+    assert!(out.contains("</title>\n\n  <meta"), "{out}");
+}
+
+/// The other special elements render their children in place and follow the normal rules.
+#[test]
+fn other_special_elements_follow_element_rules() {
+    let body = "\n  <b>a</b>\n\n  <i>b</i>\n  text\n";
+    for config in [StripConfig::default(), keeping_config()] {
+        let expected = strip_whitespace_no_sourcemap(
+            &format!("<span>{body}</span>"),
+            Language::Svelte,
+            &config,
+        )
+        .unwrap();
+        for (open, close) in [
+            ("<svelte:boundary>", "</svelte:boundary>"),
+            ("<svelte:fragment slot=\"x\">", "</svelte:fragment>"),
+            ("<svelte:element this={tag}>", "</svelte:element>"),
+        ] {
+            let src = format!("{open}{body}{close}");
+            let out = strip_whitespace_no_sourcemap(&src, Language::Svelte, &config).unwrap();
+            let name = &close[2..close.len() - 1];
+            assert_eq!(
+                out.replacen(&open[..open.len() - 1], "<span", 1)
+                    .replace(&format!("</{name}"), "</span"),
+                expected,
+                "{src:?}"
+            );
+        }
+    }
+
+    let src = "<svelte:window on:keydown={onKey} />\n<svelte:body on:click={onClick} />\n<p>a</p>";
+    let out = strip_whitespace_no_sourcemap(src, Language::Svelte, &StripConfig::default());
+    assert_eq!(
+        out.unwrap(),
+        "<svelte:window on:keydown={onKey} \n/><svelte:body on:click={onClick} \n/><p>a</p>"
+    );
+}
+
+/// Markers and tag rules take precedence over the `<svelte:head>` default.
+#[test]
+fn markers_and_tag_rules_override_svelte_head() {
+    let src = HEAD.replacen(
+        "<svelte:head>",
+        "<svelte:head data-strip-whitespace=\"off\">",
+        1,
+    );
+    let out = strip_whitespace_no_sourcemap(&src, Language::Svelte, &keeping_config()).unwrap();
+    assert_eq!(out, src);
+
+    let config = StripConfig {
+        tag_rules: vec![TagRule::new("svelte:head", TagAction::CollapseToSpace)],
+        ..keeping_config()
+    };
+    let out = strip_whitespace_no_sourcemap(HEAD, Language::Svelte, &config).unwrap();
+    assert_eq!(
+        out,
+        "<svelte:head> <title>Home</title>\n\n  <meta name=\"x\" content=\"y\" /> <link rel=\"icon\" href=\"/favicon.png\" /> </svelte:head>"
+    );
+}
+
+/// Incremental updates in elements nested inside `<svelte:head>` match full runs.
+#[test]
+fn svelte_head_incremental_updates_match_full_runs() {
+    let mut stripper = Stripper::new(keeping_config());
+    let src = "<svelte:head>\n  <noscript>\n    <link rel=\"a\" />\n\n    <link rel=\"b\" />\n  </noscript>\n</svelte:head>";
+    let mut session = stripper.start_session(src, Language::Svelte).unwrap();
+
+    let at = src.find("\"b\"").unwrap();
+    let change = TextChange::new(at + 1..at + 2, "stylesheet");
+    stripper.update_session(&mut session, &[change]).unwrap();
+
+    let full = stripper
+        .strip_no_sourcemap(session.source(), Language::Svelte)
+        .unwrap();
+    assert_eq!(session.code().unwrap(), full);
+    assert!(!full.contains("/>\n\n    <link"), "{full}");
+}
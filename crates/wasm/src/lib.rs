@@ -3,13 +3,22 @@
 //! This module exposes the whitespace stripper to JavaScript via `wasm-bindgen`.
 //! The underlying logic lives in the [`strip_whitespace`] crate.
 
+use std::time::Duration;
+
 use wasm_bindgen::prelude::*;
 
 use strip_whitespace::{
-    Language as LanguageInner,
+    Diagnostic, GapStrategy as GapStrategyInner, Language as LanguageInner, StripError,
+    report::{
+        DelimRotations as DelimRotationsInner, StripReport as StripReportInner,
+        Suppressions as SuppressionsInner,
+    },
     strip::{
-        CodeAndSourcemap, StripConfig as StripConfigInner, strip_whitespace,
-        strip_whitespace_no_sourcemap,
+        AttributeWhitespace as AttributeWhitespaceInner, CodeAndSourcemap,
+        ProtectedSpanPolicy as ProtectedSpanPolicyInner, StripConfig as StripConfigInner,
+        SyntaxErrorPolicy as SyntaxErrorPolicyInner, TagAction as TagActionInner,
+        TagRule as TagRuleInner, TextWhitespace as TextWhitespaceInner, strip_whitespace,
+        strip_whitespace_no_sourcemap, strip_whitespace_with_input_map,
     },
 };
 
@@ -20,6 +29,8 @@ use strip_whitespace::{
 pub enum Language {
     Astro,
     Svelte,
+    Vue,
+    Html,
 }
 
 impl From<Language> for LanguageInner {
@@ -27,23 +38,359 @@ impl From<Language> for LanguageInner {
         match value {
             Language::Astro => LanguageInner::Astro,
             Language::Svelte => LanguageInner::Svelte,
+            Language::Vue => LanguageInner::Vue,
+            Language::Html => LanguageInner::Html,
+        }
+    }
+}
+
+/// Policy for inputs containing syntax errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum SyntaxErrorPolicy {
+    /// Fail on the first syntax error
+    Strict,
+    /// Leave containers with syntax errors untouched
+    SkipErrorSubtrees,
+    /// Strip around syntax errors
+    #[default]
+    Lenient,
+}
+
+impl From<SyntaxErrorPolicy> for SyntaxErrorPolicyInner {
+    fn from(value: SyntaxErrorPolicy) -> Self {
+        match value {
+            SyntaxErrorPolicy::Strict => SyntaxErrorPolicyInner::Strict,
+            SyntaxErrorPolicy::SkipErrorSubtrees => SyntaxErrorPolicyInner::SkipErrorSubtrees,
+            SyntaxErrorPolicy::Lenient => SyntaxErrorPolicyInner::Lenient,
+        }
+    }
+}
+
+/// Policy for edits that would change frontmatter or `<script>`/`<style>` contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum ProtectedSpanPolicy {
+    /// Fail on the first such edit
+    #[default]
+    Strict,
+    /// Drop such edits
+    Lenient,
+}
+
+impl From<ProtectedSpanPolicy> for ProtectedSpanPolicyInner {
+    fn from(value: ProtectedSpanPolicy) -> Self {
+        match value {
+            ProtectedSpanPolicy::Strict => ProtectedSpanPolicyInner::Strict,
+            ProtectedSpanPolicy::Lenient => ProtectedSpanPolicyInner::Lenient,
+        }
+    }
+}
+
+/// How whitespace runs inside text nodes are rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum TextWhitespace {
+    /// Leave text nodes untouched
+    #[default]
+    Preserve,
+    /// Collapse each run between words to a single space
+    CollapseToSpace,
+    /// Collapse each run to a single line break if it contains one, else to a space
+    CollapseToNewline,
+}
+
+impl From<TextWhitespace> for TextWhitespaceInner {
+    fn from(value: TextWhitespace) -> Self {
+        match value {
+            TextWhitespace::Preserve => TextWhitespaceInner::Preserve,
+            TextWhitespace::CollapseToSpace => TextWhitespaceInner::CollapseToSpace,
+            TextWhitespace::CollapseToNewline => TextWhitespaceInner::CollapseToNewline,
+        }
+    }
+}
+
+/// How whitespace between the attributes of a tag is rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum AttributeWhitespace {
+    /// Leave tags untouched
+    #[default]
+    Preserve,
+    /// Collapse each run to a single space
+    CollapseToSpace,
+    /// Collapse runs within a line, keeping line breaks and indentation
+    KeepLineBreaks,
+}
+
+impl From<AttributeWhitespace> for AttributeWhitespaceInner {
+    fn from(value: AttributeWhitespace) -> Self {
+        match value {
+            AttributeWhitespace::Preserve => AttributeWhitespaceInner::Preserve,
+            AttributeWhitespace::CollapseToSpace => AttributeWhitespaceInner::CollapseToSpace,
+            AttributeWhitespace::KeepLineBreaks => AttributeWhitespaceInner::KeepLineBreaks,
         }
     }
 }
 
+/// How whitespace gaps between nodes are rewritten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum GapStrategy {
+    /// Move the whitespace inside the neighbouring tags, keeping line/column positions
+    #[default]
+    Rotate,
+    /// Delete the whitespace; positions shift, so rely on the sourcemap
+    Delete,
+}
+
+impl From<GapStrategy> for GapStrategyInner {
+    fn from(value: GapStrategy) -> Self {
+        match value {
+            GapStrategy::Rotate => GapStrategyInner::Rotate,
+            GapStrategy::Delete => GapStrategyInner::Delete,
+        }
+    }
+}
+
+/// How a tag rule rewrites the elements it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub enum TagAction {
+    /// Strip the element, even if it is whitespace-sensitive
+    Strip,
+    /// Leave the element and the gaps around it untouched
+    Preserve,
+    /// Strip the element's contents but not the gaps around it
+    StripChildren,
+    /// Strip the gaps around the element but not its contents
+    StripOutside,
+    /// Collapse the gaps inside the element to a single space
+    CollapseToSpace,
+}
+
+impl From<TagAction> for TagActionInner {
+    fn from(value: TagAction) -> Self {
+        match value {
+            TagAction::Strip => TagActionInner::Strip,
+            TagAction::Preserve => TagActionInner::Preserve,
+            TagAction::StripChildren => TagActionInner::StripChildren,
+            TagAction::StripOutside => TagActionInner::StripOutside,
+            TagAction::CollapseToSpace => TagActionInner::CollapseToSpace,
+        }
+    }
+}
+
+/// Applies `action` to elements whose tag name matches `pattern`.
+#[derive(Debug, Clone, serde::Deserialize, tsify::Tsify)]
+#[tsify(from_wasm_abi)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRule {
+    /// Tag or component name, matched exactly (case-insensitively in HTML); `*` matches any run
+    /// of characters
+    pub pattern: String,
+    /// What to do with matching elements
+    pub action: TagAction,
+}
+
+impl From<TagRule> for TagRuleInner {
+    fn from(value: TagRule) -> Self {
+        TagRuleInner::new(value.pattern, value.action.into())
+    }
+}
+
 /// Configuration options for whitespace stripping.
-#[derive(Debug, Clone, Copy, Default, serde::Deserialize, tsify::Tsify)]
+#[derive(Debug, Clone, Default, serde::Deserialize, tsify::Tsify)]
 #[tsify(from_wasm_abi)]
 #[serde(rename_all = "camelCase")]
 pub struct StripConfig {
     /// Preserve blank-line gaps ("\n\n" / "\r\n\r\n") between nodes
     pub preserve_blank_lines: bool,
+    /// How to handle inputs with syntax errors (defaults to "lenient")
+    #[serde(default)]
+    #[tsify(optional)]
+    pub syntax_errors: SyntaxErrorPolicy,
+    /// How to handle edits that would change frontmatter or `<script>`/`<style>` contents
+    /// (defaults to "strict")
+    #[serde(default)]
+    #[tsify(optional)]
+    pub protected_spans: ProtectedSpanPolicy,
+    /// Maximum time in milliseconds spent parsing before failing (no limit by default).
+    ///
+    /// This is the only way to bound a call here: the core's cancellation flag is not exposed,
+    /// because a call runs synchronously and JavaScript cannot set the flag until it returns.
+    #[serde(default)]
+    #[tsify(optional)]
+    pub parse_timeout_ms: Option<u32>,
+    /// How whitespace inside text nodes is rewritten (defaults to "preserve")
+    #[serde(default)]
+    #[tsify(optional)]
+    pub text_whitespace: TextWhitespace,
+    /// Delete whitespace between text and block-level element boundaries instead of rotating it
+    #[serde(default)]
+    #[tsify(optional)]
+    pub trim_block_text: bool,
+    /// Collapse gaps between inline neighbours to a single space instead of removing them
+    #[serde(default)]
+    #[tsify(optional)]
+    pub inline_safe: bool,
+    /// Component names rendered as blocks, in addition to the HTML block-level elements
+    #[serde(default)]
+    #[tsify(optional)]
+    pub block_level_components: Vec<String>,
+    /// Remove `data-strip-whitespace` attributes from the output
+    #[serde(default)]
+    #[tsify(optional)]
+    pub remove_marker_attributes: bool,
+    /// Per-tag rules; the first rule matching an element applies
+    #[serde(default)]
+    #[tsify(optional)]
+    pub tag_rules: Vec<TagRule>,
+    /// How gaps between nodes are rewritten (defaults to "rotate")
+    #[serde(default)]
+    #[tsify(optional)]
+    pub gap_strategy: GapStrategy,
+    /// How whitespace between attributes is rewritten (defaults to "preserve")
+    #[serde(default)]
+    #[tsify(optional)]
+    pub attribute_whitespace: AttributeWhitespace,
+    /// Remove HTML comments, except directives, conditional and license comments
+    #[serde(default)]
+    #[tsify(optional)]
+    pub remove_comments: bool,
 }
 
 impl From<StripConfig> for StripConfigInner {
     fn from(value: StripConfig) -> Self {
         StripConfigInner {
             preserve_blank_lines: value.preserve_blank_lines,
+            syntax_errors: value.syntax_errors.into(),
+            protected_spans: value.protected_spans.into(),
+            parse_timeout: value
+                .parse_timeout_ms
+                .map(|ms| Duration::from_millis(ms.into())),
+            text_whitespace: value.text_whitespace.into(),
+            trim_block_text: value.trim_block_text,
+            inline_safe: value.inline_safe,
+            block_level_components: value.block_level_components,
+            remove_marker_attributes: value.remove_marker_attributes,
+            tag_rules: value.tag_rules.into_iter().map(Into::into).collect(),
+            gap_strategy: value.gap_strategy.into(),
+            attribute_whitespace: value.attribute_whitespace.into(),
+            remove_comments: value.remove_comments,
+            ..Default::default()
+        }
+    }
+}
+
+/// Number of trailing-delimiter rotations, by delimiter.
+#[derive(Debug, Clone, Copy, serde::Serialize, tsify::Tsify)]
+#[serde(rename_all = "camelCase")]
+pub struct DelimRotations {
+    /// `>` of a start/end tag
+    pub gt: usize,
+    /// `/>` of a self-closing tag
+    pub slash_gt: usize,
+    /// `-->` of a comment
+    pub comment_end: usize,
+    /// `}` of an expression or block tag
+    pub r_brace: usize,
+    /// `}}` of a Vue interpolation
+    pub double_r_brace: usize,
+}
+
+impl From<DelimRotationsInner> for DelimRotations {
+    fn from(value: DelimRotationsInner) -> Self {
+        DelimRotations {
+            gt: value.gt,
+            slash_gt: value.slash_gt,
+            comment_end: value.comment_end,
+            r_brace: value.r_brace,
+            double_r_brace: value.double_r_brace,
+        }
+    }
+}
+
+/// Directives that kept parts of the file from being stripped.
+#[derive(Debug, Clone, Copy, serde::Serialize, tsify::Tsify)]
+#[serde(rename_all = "camelCase")]
+pub struct Suppressions {
+    /// The file contains `<!-- strip-whitespace-ignore-file -->`, so nothing was stripped
+    pub ignore_file: bool,
+    /// The Svelte file sets `<svelte:options preserveWhitespace />`, so nothing was stripped
+    pub svelte_preserve_whitespace: bool,
+    /// Astro elements with `is:raw` whose contents were left untouched
+    pub astro_raw_elements: usize,
+}
+
+impl From<SuppressionsInner> for Suppressions {
+    fn from(value: SuppressionsInner) -> Self {
+        Suppressions {
+            ignore_file: value.ignore_file,
+            svelte_preserve_whitespace: value.svelte_preserve_whitespace,
+            astro_raw_elements: value.astro_raw_elements,
+        }
+    }
+}
+
+/// Statistics about what a stripping run did.
+#[derive(Debug, Clone, Copy, serde::Serialize, tsify::Tsify)]
+#[serde(rename_all = "camelCase")]
+pub struct StripReport {
+    /// Whitespace-only gaps considered for rewriting
+    pub gaps_examined: usize,
+    /// Gaps rewritten by rotating a trailing delimiter, by delimiter
+    pub delim_rotations: DelimRotations,
+    /// Gaps rewritten by rotating an opener prefix
+    pub prefix_rotations: usize,
+    /// Gaps skipped because of `preserveBlankLines`
+    pub skipped_blank_lines: usize,
+    /// Whitespace runs inside text nodes collapsed because of `textWhitespace`
+    pub collapsed_text_runs: usize,
+    /// Whitespace ranges next to block-level boundaries deleted because of `trimBlockText`
+    pub trimmed_block_text: usize,
+    /// Gaps collapsed to a single space because of `inlineSafe` or a "collapseToSpace" tag rule
+    pub inline_gaps: usize,
+    /// `data-strip-whitespace` attributes removed because of `removeMarkerAttributes`
+    pub removed_marker_attributes: usize,
+    /// Gaps between two nodes deleted outright, e.g. by the "delete" gap strategy
+    pub deleted_gaps: usize,
+    /// Gaps rewritten by a custom gap rewriter. Custom rewriters can only be set from Rust, so
+    /// this is always 0 here
+    pub custom_gaps: usize,
+    /// Whitespace runs between attributes collapsed because of `attributeWhitespace`
+    pub collapsed_attribute_gaps: usize,
+    /// Comments deleted because of `removeComments`
+    pub removed_comments: usize,
+    /// Directives that suppressed stripping
+    pub suppressions: Suppressions,
+    /// Estimated bytes saved once the output is minified or compiled
+    pub estimated_bytes_saved: usize,
+}
+
+impl From<StripReportInner> for StripReport {
+    fn from(value: StripReportInner) -> Self {
+        StripReport {
+            gaps_examined: value.gaps_examined,
+            delim_rotations: value.delim_rotations.into(),
+            prefix_rotations: value.prefix_rotations,
+            skipped_blank_lines: value.skipped_blank_lines,
+            collapsed_text_runs: value.collapsed_text_runs,
+            trimmed_block_text: value.trimmed_block_text,
+            inline_gaps: value.inline_gaps,
+            removed_marker_attributes: value.removed_marker_attributes,
+            deleted_gaps: value.deleted_gaps,
+            custom_gaps: value.custom_gaps,
+            collapsed_attribute_gaps: value.collapsed_attribute_gaps,
+            removed_comments: value.removed_comments,
+            suppressions: value.suppressions.into(),
+            estimated_bytes_saved: value.estimated_bytes_saved,
         }
     }
 }
@@ -56,6 +403,8 @@ pub struct StripOutput {
     pub code: String,
     /// The generated/re-written sourcemap JSON.
     pub map: String,
+    /// Statistics about what the rewrite did.
+    pub report: StripReport,
 }
 
 impl From<CodeAndSourcemap> for StripOutput {
@@ -63,14 +412,57 @@ impl From<CodeAndSourcemap> for StripOutput {
         StripOutput {
             code: value.code,
             map: value.map,
+            report: value.report.into(),
         }
     }
 }
 
+/// Position of a stripping error in the input, attached to thrown errors as `diagnostic`.
+#[derive(Debug, Clone, serde::Serialize, tsify::Tsify)]
+#[serde(rename_all = "camelCase")]
+pub struct StripDiagnostic {
+    /// The error message, without position information
+    pub message: String,
+    /// The source name passed in, or `"<input>"`
+    pub file_name: String,
+    /// 1-based line of the error
+    pub line: usize,
+    /// 1-based column of the error, in UTF-16 code units
+    pub column: usize,
+    /// Text of the offending line (empty if the error has no position)
+    pub snippet: String,
+}
+
+impl From<Diagnostic> for StripDiagnostic {
+    fn from(value: Diagnostic) -> Self {
+        StripDiagnostic {
+            message: value.message,
+            file_name: value.file_name,
+            line: value.line,
+            column: value.column,
+            snippet: value.snippet,
+        }
+    }
+}
+
+/// File name used in diagnostics when the caller does not name the input.
+const UNNAMED_INPUT: &str = "<input>";
+
+/// Converts `error` into a JS `Error` whose message is the rendered diagnostic and whose
+/// `diagnostic` property holds a [`StripDiagnostic`].
+fn to_js_error(error: StripError, source: &str, file_name: &str) -> JsValue {
+    let diagnostic = Diagnostic::new(&error, source, file_name);
+    let js_error = js_sys::Error::new(&diagnostic.render());
+    if let Ok(value) = serde_wasm_bindgen::to_value(&StripDiagnostic::from(diagnostic)) {
+        let _ = js_sys::Reflect::set(&js_error, &JsValue::from_str("diagnostic"), &value);
+    }
+    js_error.into()
+}
+
 /// Strip inter-node whitespace and create a brand-new sourcemap.
 ///
 /// `source_name` is recorded as the sourcemap's source filename.
-/// `language` specifies the template language (e.g., "astro" or "svelte").
+/// `language` specifies the template language (e.g., "astro", "svelte", "vue" or "html").
 #[wasm_bindgen(js_name = "stripWhitespace")]
 pub fn wasm_strip_whitespace(
     code: String,
@@ -81,13 +473,33 @@ pub fn wasm_strip_whitespace(
     console_error_panic_hook::set_once();
 
     let output = strip_whitespace(&code, &source_name, language.into(), &config.into())
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        .map_err(|e| to_js_error(e, &code, &source_name))?;
+    Ok(output.into())
+}
+
+/// Strip inter-node whitespace and rewrite an existing input sourcemap.
+///
+/// `input_map` is the sourcemap JSON produced by an upstream transform of `code`; the returned
+/// sourcemap maps back to that transform's original sources.
+/// `language` specifies the template language (e.g., "astro", "svelte", "vue" or "html").
+#[wasm_bindgen(js_name = "stripWhitespaceWithInputMap")]
+pub fn wasm_strip_whitespace_with_input_map(
+    code: String,
+    input_map: String,
+    language: Language,
+    config: StripConfig,
+) -> Result<StripOutput, JsValue> {
+    console_error_panic_hook::set_once();
+
+    let output =
+        strip_whitespace_with_input_map(&code, &input_map, language.into(), &config.into())
+            .map_err(|e| to_js_error(e, &code, UNNAMED_INPUT))?;
     Ok(output.into())
 }
 
 /// Strip inter-node whitespace without producing a sourcemap.
 ///
-/// `language` specifies the template language (e.g., "astro" or "svelte").
+/// `language` specifies the template language (e.g., "astro", "svelte", "vue" or "html").
 #[wasm_bindgen(js_name = "stripWhitespaceNoSourcemap")]
 pub fn wasm_strip_whitespace_no_sourcemap(
     code: String,
@@ -97,6 +509,6 @@ pub fn wasm_strip_whitespace_no_sourcemap(
     console_error_panic_hook::set_once();
 
     let output = strip_whitespace_no_sourcemap(&code, language.into(), &config.into())
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        .map_err(|e| to_js_error(e, &code, UNNAMED_INPUT))?;
     Ok(output)
 }
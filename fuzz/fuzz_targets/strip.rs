@@ -12,18 +12,27 @@ fuzz_target!(|data: &[u8]| {
 
     let source = String::from_utf8_lossy(data);
 
-    for &language in &[Language::Astro, Language::Svelte] {
+    for &language in &[
+        Language::Astro,
+        Language::Svelte,
+        Language::Vue,
+        Language::Html,
+    ] {
         for config in &[
             StripConfig {
                 preserve_blank_lines: false,
+                ..Default::default()
             },
             StripConfig {
                 preserve_blank_lines: true,
+                ..Default::default()
             },
         ] {
             let filename = match language {
                 Language::Astro => "input.astro",
                 Language::Svelte => "input.svelte",
+                Language::Vue => "input.vue",
+                Language::Html => "input.html",
             };
 
             if let Ok(out) = strip_whitespace(&source, filename, language, config) {
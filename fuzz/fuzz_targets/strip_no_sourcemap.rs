@@ -15,13 +15,20 @@ fuzz_target!(|data: &[u8]| {
 
     // Exercise both configuration modes for every input.
     // Parse errors are expected outcomes and must never crash.
-    for &language in &[Language::Astro, Language::Svelte] {
+    for &language in &[
+        Language::Astro,
+        Language::Svelte,
+        Language::Vue,
+        Language::Html,
+    ] {
         for config in &[
             StripConfig {
                 preserve_blank_lines: false,
+                ..Default::default()
             },
             StripConfig {
                 preserve_blank_lines: true,
+                ..Default::default()
             },
         ] {
             let _ = strip_whitespace_no_sourcemap(&source, language, config);
@@ -14,10 +14,13 @@ import type {
 
 export type { Language, StripWhitespaceOptions } from "./types";
 
-export function defaultLanguageSelector(
-  id: string,
-  _content: string,
-): Language | false {
+/**
+ * Languages processed by {@link defaultLanguageSelector}.
+ * Other languages are only processed when listed in `selectLanguage`.
+ */
+const DEFAULT_LANGUAGES: readonly Language[] = ["astro", "svelte"];
+
+function detectLanguage(id: string): Language | false {
   if (
     id.includes("?") ||
     id.includes("node_modules/") ||
@@ -34,12 +37,31 @@ export function defaultLanguageSelector(
     return "svelte";
   }
 
+  if (id.endsWith(".vue")) {
+    return "vue";
+  }
+
+  if (id.endsWith(".html")) {
+    return "html";
+  }
+
+  return false;
+}
+
+export function defaultLanguageSelector(
+  id: string,
+  _content: string,
+): Language | false {
+  const lang = detectLanguage(id);
+  if (lang && DEFAULT_LANGUAGES.includes(lang)) {
+    return lang;
+  }
   return false;
 }
 
 function createLanguageSelectorFromArray(languages: readonly Language[]) {
-  return (id: string, content: string): Language | false => {
-    const lang = defaultLanguageSelector(id, content);
+  return (id: string, _content: string): Language | false => {
+    const lang = detectLanguage(id);
     if (lang && languages.includes(lang)) {
       return lang;
     }
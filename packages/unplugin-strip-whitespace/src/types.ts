@@ -1,7 +1,7 @@
 /**
  * Supported languages for whitespace stripping.
  */
-export type Language = "astro" | "svelte";
+export type Language = "astro" | "svelte" | "vue" | "html";
 
 /**
  * Kinds of bundlers where plugin reordering is supported.
@@ -32,6 +32,7 @@ export interface StripWhitespaceOptions {
    * If an array is provided, only files with matching languages will be processed.
    *
    * By default, the plugin processes `.astro` and `.svelte` files excluding those in `node_modules` or with query parameters.
+   * `.vue` and `.html` files are only processed when their language is listed explicitly, e.g. `["astro", "svelte", "vue"]`.
    *
    * @default defaultLanguageSelector
    */
@@ -23,9 +23,14 @@ it.for(fixtures)(
       throw new Error(`Invalid fixture filename: ${fixtureFilename}`);
     }
 
-    const language = ({ ".astro": "astro", ".svelte": "svelte" } as const)[
-      extension
-    ];
+    const language = (
+      {
+        ".astro": "astro",
+        ".svelte": "svelte",
+        ".vue": "vue",
+        ".html": "html",
+      } as const
+    )[extension];
     if (!language) {
       throw new Error(`Unsupported fixture extension: ${extension}`);
     }
@@ -22,6 +22,8 @@ function getLanguageFromFilename(filename: string) {
     {
       astro: "astro",
       svelte: "svelte",
+      vue: "vue",
+      html: "html",
     } as const
   )[extension];
 